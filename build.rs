@@ -0,0 +1,21 @@
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_header();
+}
+
+// The header is written to OUT_DIR so builds never touch the source tree.
+// CI checks that the copy in include/ matches it.
+#[cfg(feature = "capi")]
+fn generate_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).unwrap())
+        .generate()
+        .expect("Unable to generate C bindings")
+        .write_to_file(format!("{}/qdollar.h", out_dir));
+}
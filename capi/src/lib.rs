@@ -0,0 +1,4 @@
+//! Builds `qdollar::capi` as `libqdollar_capi.so` and `libqdollar_capi.a`.
+//! The header is `include/qdollar.h` in the qdollar crate.
+
+pub use qdollar::capi::*;
@@ -0,0 +1,108 @@
+#ifndef QDOLLAR_H
+#define QDOLLAR_H
+
+/* Generated by cbindgen from src/capi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum QDollarStatus {
+  Q_DOLLAR_STATUS_OK = 0,
+  Q_DOLLAR_STATUS_NULL_POINTER = 1,
+  Q_DOLLAR_STATUS_INVALID_STRING = 2,
+  Q_DOLLAR_STATUS_BUFFER_TOO_SMALL = 3,
+  Q_DOLLAR_STATUS_NO_REGISTERED_GESTURES = 4,
+  Q_DOLLAR_STATUS_SERIALIZATION = 5,
+  Q_DOLLAR_STATUS_IO = 6,
+  Q_DOLLAR_STATUS_INVALID_TEMPLATE = 7,
+  Q_DOLLAR_STATUS_UNSUPPORTED = 8,
+  Q_DOLLAR_STATUS_EMPTY_GESTURE = 9,
+  /**
+   * The call panicked. The recognizer may be left partly updated.
+   */
+  Q_DOLLAR_STATUS_PANIC = 10,
+  Q_DOLLAR_STATUS_CONFIG_MISMATCH = 11,
+} QDollarStatus;
+
+typedef struct QDollarRecognizer QDollarRecognizer;
+
+typedef struct QDollarPoint {
+  double x;
+  double y;
+  uint32_t stroke_id;
+} QDollarPoint;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an empty recognizer, or returns NULL on failure. Free it with
+ * `qdollar_recognizer_free`.
+ */
+struct QDollarRecognizer *qdollar_recognizer_new(void);
+
+/**
+ * # Safety
+ * `recognizer` must come from `qdollar_recognizer_new` and not be used
+ * afterwards. NULL is ignored.
+ */
+void qdollar_recognizer_free(struct QDollarRecognizer *recognizer);
+
+/**
+ * Adds a template. `name` is a NUL-terminated UTF-8 string and `points`
+ * holds `len` points, at least one. The new template count is written to
+ * `count` unless it is NULL.
+ *
+ * # Safety
+ * All pointers must be valid for the given lengths.
+ */
+enum QDollarStatus qdollar_add_gesture(struct QDollarRecognizer *recognizer,
+                                       const char *name,
+                                       const struct QDollarPoint *points,
+                                       size_t len,
+                                       size_t *count);
+
+/**
+ * Recognizes a gesture, writing the NUL-terminated name of the best match
+ * into `name` (`name_capacity` bytes) and its score into `score`.
+ *
+ * # Safety
+ * All pointers must be valid for the given lengths.
+ */
+enum QDollarStatus qdollar_recognize(const struct QDollarRecognizer *recognizer,
+                                     const struct QDollarPoint *points,
+                                     size_t len,
+                                     char *name,
+                                     size_t name_capacity,
+                                     double *score);
+
+/**
+ * Saves the recognizer's templates as JSON.
+ *
+ * # Safety
+ * `path` must be a valid NUL-terminated string.
+ */
+enum QDollarStatus qdollar_save(const struct QDollarRecognizer *recognizer, const char *path);
+
+/**
+ * Replaces the recognizer's templates with the ones saved at `path`.
+ *
+ * # Safety
+ * `path` must be a valid NUL-terminated string.
+ */
+enum QDollarStatus qdollar_load(struct QDollarRecognizer *recognizer, const char *path);
+
+/**
+ * A static, NUL-terminated description of `status`.
+ */
+const char *qdollar_status_message(enum QDollarStatus status);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* QDOLLAR_H */
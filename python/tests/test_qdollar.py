@@ -0,0 +1,75 @@
+# Run from a virtualenv:
+#   pip install maturin pytest numpy && maturin develop && pytest python/tests
+import numpy as np
+import pytest
+
+import qdollar
+
+SQUARE = [(0.0, 0.0, 1), (0.0, 1.0, 1), (1.0, 1.0, 1), (1.0, 0.0, 1), (0.0, 0.0, 1)]
+TRIANGLE = [(0.0, 0.0, 1), (0.5, 1.0, 1), (1.0, 0.0, 1), (0.0, 0.0, 1)]
+
+
+def make_recognizer():
+    recognizer = qdollar.QDollarRecognizer()
+    recognizer.add_gesture("square", np.array(SQUARE, dtype=np.float64))
+    recognizer.add_gesture("triangle", np.array(TRIANGLE, dtype=np.float64))
+    return recognizer
+
+
+def test_recognize_numpy():
+    recognizer = make_recognizer()
+    assert len(recognizer) == 2
+
+    result = recognizer.recognize(np.array(TRIANGLE))
+    assert isinstance(result, qdollar.QDollarResult)
+    assert result.name == "triangle"
+    assert result.score > 0.9
+    assert result.time >= 0.0
+
+
+def test_recognize_points_and_tuples():
+    recognizer = make_recognizer()
+    points = [qdollar.Point(x, y, stroke) for x, y, stroke in SQUARE]
+    assert recognizer.recognize(points).name == "square"
+    assert recognizer.recognize(SQUARE).name == "square"
+
+
+def test_matches_rust_results_for_all_inputs():
+    recognizer = make_recognizer()
+    from_array = recognizer.recognize(np.array(SQUARE))
+    from_tuples = recognizer.recognize(SQUARE)
+    assert from_array.name == from_tuples.name
+    assert from_array.score == from_tuples.score
+
+
+def test_rejects_bad_arrays():
+    recognizer = make_recognizer()
+    with pytest.raises(ValueError):
+        recognizer.recognize(np.zeros((4, 2)))
+    with pytest.raises(ValueError):
+        recognizer.recognize(np.array([[0.0, 0.0, 0.5], [1.0, 1.0, 0.5]]))
+    with pytest.raises(ValueError):
+        recognizer.recognize("square")
+
+
+def test_no_registered_gestures():
+    with pytest.raises(qdollar.QDollarError):
+        qdollar.QDollarRecognizer().recognize(SQUARE)
+
+
+def test_persistence(tmp_path):
+    recognizer = make_recognizer()
+    path = tmp_path / "templates.json"
+    recognizer.save(str(path))
+
+    loaded = qdollar.QDollarRecognizer()
+    assert loaded.load(str(path)) == 2
+    assert loaded.recognize(TRIANGLE).score == recognizer.recognize(TRIANGLE).score
+
+    from_json = qdollar.QDollarRecognizer()
+    assert from_json.load_json(recognizer.to_json()) == 2
+    assert from_json.recognize(SQUARE).name == "square"
+
+    with pytest.raises(qdollar.QDollarError):
+        loaded.load(str(tmp_path / "missing.json"))
+    assert loaded.delete_user_gestures() == 0
@@ -0,0 +1,235 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde::Serialize;
+
+use crate::distance::CloudDistance;
+use crate::error::QDollarError;
+use crate::utils::MatchCounters;
+use crate::{PointCloud, QDollarConfig};
+
+// Share of the mean distance between classes that stands in for the spread
+// when no class in the vocabulary has two templates.
+const FALLBACK_SPREAD_FRACTION: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DistanceMatrix {
+    pub classes: Vec<String>,
+    /// Off the diagonal: the closest template pair of two classes. On the
+    /// diagonal: the mean pairwise distance within the class (its spread).
+    pub distances: Vec<Vec<f64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Collision {
+    pub class_a: String,
+    pub class_b: String,
+    pub distance: f64,
+    /// The larger spread of the two classes, or the fallback spread when
+    /// neither has one.
+    pub spread: f64,
+    /// `distance / spread`, or 0 for identical templates.
+    pub ratio: f64,
+    /// Indices of the closest pair of templates, the likely culprits.
+    pub template_a: usize,
+    pub template_b: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfusabilityReport {
+    pub matrix: DistanceMatrix,
+    pub collisions: Vec<Collision>,
+}
+
+impl ConfusabilityReport {
+    pub fn to_json(&self) -> Result<String, QDollarError> {
+        serde_json::to_string_pretty(self).map_err(|e| QDollarError::Serialization(e.to_string()))
+    }
+
+    pub fn matrix_csv(&self) -> String {
+        let mut csv = String::from("class");
+        for class in &self.matrix.classes {
+            csv.push(',');
+            csv.push_str(&csv_field(class));
+        }
+        csv.push('\n');
+        for (class, row) in self.matrix.classes.iter().zip(&self.matrix.distances) {
+            csv.push_str(&csv_field(class));
+            for distance in row {
+                csv.push_str(&format!(",{}", distance));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn collisions_csv(&self) -> String {
+        let mut csv = String::from("class_a,class_b,distance,spread,ratio,template_a,template_b\n");
+        for c in &self.collisions {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                csv_field(&c.class_a),
+                csv_field(&c.class_b),
+                c.distance,
+                c.spread,
+                c.ratio,
+                c.template_a,
+                c.template_b
+            ));
+        }
+        csv
+    }
+
+    pub fn collisions_with<'a>(&'a self, class: &'a str) -> impl Iterator<Item = &'a Collision> {
+        self.collisions
+            .iter()
+            .filter(move |c| c.class_a == class || c.class_b == class)
+    }
+}
+
+// Flags class pairs whose closest templates are within `ratio` times the
+// larger of the two class spreads, comparing templates with the distance
+// `config` recognizes with. A class needs two templates to have a spread.
+// When neither class of a pair has one, the mean spread of the classes that
+// do is used, or, if no class does, a fraction of the mean distance between
+// classes.
+pub fn analyze(
+    config: &QDollarConfig,
+    templates: &[PointCloud],
+    ratio: f64,
+) -> ConfusabilityReport {
+    let mut classes: Vec<String> = Vec::new();
+    for template in templates {
+        if !classes.contains(&template.name) {
+            classes.push(template.name.clone());
+        }
+    }
+    let class_of: Vec<usize> = templates
+        .iter()
+        .map(|t| classes.iter().position(|c| *c == t.name).unwrap())
+        .collect();
+
+    let distance = config.distance();
+    let k = classes.len();
+    let mut closest = vec![vec![(f64::INFINITY, 0, 0); k]; k];
+    let mut spread_sum = vec![0.0; k];
+    let mut spread_pairs = vec![0usize; k];
+    for i in 0..templates.len() {
+        for j in i + 1..templates.len() {
+            let d = distance.distance(
+                &templates[i],
+                &templates[j],
+                f64::INFINITY,
+                &mut MatchCounters::default(),
+            );
+            let (a, b) = (class_of[i], class_of[j]);
+            if a == b {
+                spread_sum[a] += d;
+                spread_pairs[a] += 1;
+            } else if d < closest[a][b].0 {
+                closest[a][b] = (d, i, j);
+                closest[b][a] = (d, j, i);
+            }
+        }
+    }
+    let spread: Vec<f64> = spread_sum
+        .iter()
+        .zip(&spread_pairs)
+        .map(|(&sum, &pairs)| if pairs > 0 { sum / pairs as f64 } else { 0.0 })
+        .collect();
+    let fallback_spread = fallback_spread(&spread, &spread_pairs, &closest);
+
+    let mut distances = vec![vec![0.0; k]; k];
+    let mut collisions = Vec::new();
+    for a in 0..k {
+        distances[a][a] = spread[a];
+        for b in 0..k {
+            if a == b {
+                continue;
+            }
+            let (distance, template_a, template_b) = closest[a][b];
+            distances[a][b] = distance;
+            let mut pair_spread = spread[a].max(spread[b]);
+            if pair_spread == 0.0 {
+                pair_spread = fallback_spread;
+            }
+            let pair_ratio = if distance == 0.0 {
+                0.0
+            } else if pair_spread > 0.0 {
+                distance / pair_spread
+            } else {
+                continue;
+            };
+            if a < b && pair_ratio <= ratio {
+                collisions.push(Collision {
+                    class_a: classes[a].clone(),
+                    class_b: classes[b].clone(),
+                    distance,
+                    spread: pair_spread,
+                    ratio: pair_ratio,
+                    template_a,
+                    template_b,
+                });
+            }
+        }
+    }
+    collisions.sort_by(|x, y| x.ratio.total_cmp(&y.ratio));
+
+    ConfusabilityReport {
+        matrix: DistanceMatrix { classes, distances },
+        collisions,
+    }
+}
+
+// Collisions a proposed gesture class would introduce into `templates`.
+pub fn vet_gesture(
+    config: &QDollarConfig,
+    templates: &[PointCloud],
+    proposed: &[PointCloud],
+    ratio: f64,
+) -> Vec<Collision> {
+    let combined = [templates, proposed].concat();
+    let report = analyze(config, &combined, ratio);
+    let names: Vec<&str> = proposed.iter().map(|t| t.name.as_str()).collect();
+    report
+        .collisions
+        .into_iter()
+        .filter(|c| names.contains(&c.class_a.as_str()) || names.contains(&c.class_b.as_str()))
+        .collect()
+}
+
+fn fallback_spread(
+    spread: &[f64],
+    spread_pairs: &[usize],
+    closest: &[Vec<(f64, usize, usize)>],
+) -> f64 {
+    let spreads: Vec<f64> = spread
+        .iter()
+        .zip(spread_pairs)
+        .filter(|&(_, &pairs)| pairs > 0)
+        .map(|(&spread, _)| spread)
+        .collect();
+    if !spreads.is_empty() {
+        return spreads.iter().sum::<f64>() / spreads.len() as f64;
+    }
+    let between: Vec<f64> = closest
+        .iter()
+        .enumerate()
+        .flat_map(|(a, row)| row.iter().skip(a + 1).map(|&(d, _, _)| d))
+        .filter(|d| d.is_finite())
+        .collect();
+    if between.is_empty() {
+        return 0.0;
+    }
+    FALLBACK_SPREAD_FRACTION * between.iter().sum::<f64>() / between.len() as f64
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,81 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Minimum-cost assignment of `rows` to distinct columns out of `cols`, by
+/// the Hungarian method with potentials in O(rows² · cols).
+///
+/// Returns the column chosen for each row and the total cost. `rows` must not
+/// exceed `cols`, and `cost(i, j)` must be finite.
+pub fn min_cost_assignment<F>(rows: usize, cols: usize, cost: F) -> (Vec<usize>, f64)
+where
+    F: Fn(usize, usize) -> f64,
+{
+    assert!(rows <= cols, "more rows than columns");
+
+    // 1-based, with row and column 0 as the unassigned sentinel.
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; cols + 1];
+    let mut row_of = vec![0; cols + 1];
+    let mut way = vec![0; cols + 1];
+
+    for i in 1..=rows {
+        row_of[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; cols + 1];
+        let mut used = vec![false; cols + 1];
+
+        // Grow a shortest augmenting path from row i until it reaches a free
+        // column, adjusting the potentials so reduced costs stay non-negative.
+        loop {
+            used[j0] = true;
+            let i0 = row_of[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=cols {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(i0 - 1, j - 1) - u[i0] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=cols {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if row_of[j0] == 0 {
+                break;
+            }
+        }
+
+        while j0 != 0 {
+            let j1 = way[j0];
+            row_of[j0] = row_of[j1];
+            j0 = j1;
+        }
+    }
+
+    let mut assignment = vec![0; rows];
+    for j in 1..=cols {
+        if row_of[j] != 0 {
+            assignment[row_of[j] - 1] = j - 1;
+        }
+    }
+    let total = assignment
+        .iter()
+        .enumerate()
+        .map(|(i, &j)| cost(i, j))
+        .sum();
+    (assignment, total)
+}
@@ -0,0 +1,441 @@
+use alloc::format;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::clock;
+use crate::compiled::{self, CompiledRecognizer, Snapshot, TemplateEntry, LUT_LEN, POINTS_LEN};
+use crate::error::QDollarError;
+use crate::index::{Features, NUM_FEATURES};
+use crate::metadata::TemplateMetadata;
+use crate::utils::{LUT_SIZE, NUM_POINTS};
+use crate::{Precision, Preprocess, QDollarConfig, ResampleStrategy};
+
+// A file is a fixed header, a table of the templates' names, metadata and
+// features, then the points and LUTs exactly as `CompiledRecognizer` reads
+// them, starting 8-byte aligned. Every number is little-endian.
+//
+// Header:
+//    0  magic
+//    8  u16 FORMAT_VERSION
+//   10  u16 BYTE_ORDER_MARK
+//   12  u16 LUT_SIZE
+//   14  u16 points per template, NUM_POINTS
+//   16  u32 template count
+//   20  u32 config_fingerprint of the config the templates were built with
+//   24  u64 table length
+//   32  u64 offset of the points and LUTs
+//   40  u64 length of the points and LUTs
+//   48  u64 checksum of the header up to here, the table and the padding
+//   56  u64 checksum of the points and LUTs
+//
+// Table, for each template:
+//   u32 stroke count, u32 name length, u32 metadata length,
+//   NUM_FEATURES f64 features, the name in UTF-8, the metadata as JSON.
+
+pub const MAGIC: [u8; 8] = *b"QDOLLARB";
+pub const FORMAT_VERSION: u16 = 2;
+/// Reads as 0xFFFE from a file written in the other byte order.
+pub const BYTE_ORDER_MARK: u16 = 0xFEFF;
+
+const HEADER_LEN: usize = 64;
+const CHECKSUMS_AT: usize = 48;
+const ENTRY_LEN: usize = 12 + NUM_FEATURES * 8;
+
+/// How much of a file loading checks against its checksums.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Verify {
+    /// The whole file. Reads every LUT, so a mapped file is paged in
+    /// entirely.
+    #[default]
+    All,
+    /// The header and template table only, so mapping a file reads none of
+    /// the LUTs up front. Corrupt points or LUTs then give wrong results, but
+    /// never a panic.
+    Header,
+}
+
+pub fn to_bytes(compiled: &CompiledRecognizer) -> Result<Vec<u8>, QDollarError> {
+    let snapshot = compiled.snapshot();
+    let mut table = Vec::with_capacity(snapshot.templates.len() * ENTRY_LEN);
+    for template in &snapshot.templates {
+        let metadata = if template.metadata.is_empty() {
+            Vec::new()
+        } else {
+            serde_json::to_vec(&template.metadata)
+                .map_err(|e| QDollarError::Serialization(e.to_string()))?
+        };
+        put_u32(&mut table, template.stroke_count)?;
+        put_u32(&mut table, template.name.len())?;
+        put_u32(&mut table, metadata.len())?;
+        for feature in template.features.0 {
+            table.extend_from_slice(&feature.to_le_bytes());
+        }
+        table.extend_from_slice(template.name.as_bytes());
+        table.extend_from_slice(&metadata);
+    }
+
+    let data = (*snapshot.data).as_ref();
+    let (points, luts) = (&data[snapshot.points.clone()], &data[snapshot.luts.clone()]);
+    let data_offset = align(HEADER_LEN + table.len());
+    let mut bytes = Vec::with_capacity(data_offset + points.len() + luts.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&BYTE_ORDER_MARK.to_le_bytes());
+    bytes.extend_from_slice(&(LUT_SIZE as u16).to_le_bytes());
+    bytes.extend_from_slice(&(NUM_POINTS as u16).to_le_bytes());
+    put_u32(&mut bytes, snapshot.templates.len())?;
+    bytes.extend_from_slice(&config_fingerprint(&snapshot.config).to_le_bytes());
+    bytes.extend_from_slice(&(table.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(data_offset as u64).to_le_bytes());
+    bytes.extend_from_slice(&((points.len() + luts.len()) as u64).to_le_bytes());
+    bytes.resize(HEADER_LEN, 0);
+    bytes.extend_from_slice(&table);
+    bytes.resize(data_offset, 0);
+    bytes.extend_from_slice(points);
+    bytes.extend_from_slice(luts);
+
+    let header_checksum = checksum(&[&bytes[..CHECKSUMS_AT], &bytes[HEADER_LEN..data_offset]]);
+    let data_checksum = checksum(&[&bytes[data_offset..]]);
+    bytes[CHECKSUMS_AT..CHECKSUMS_AT + 8].copy_from_slice(&header_checksum.to_le_bytes());
+    bytes[CHECKSUMS_AT + 8..HEADER_LEN].copy_from_slice(&data_checksum.to_le_bytes());
+    Ok(bytes)
+}
+
+/// A snapshot reading its points and LUTs straight from `bytes`. Fails with
+/// `QDollarError::ConfigMismatch` unless `config` preprocesses, resamples and
+/// matches in the precision the templates were built with.
+pub fn from_bytes<B>(
+    bytes: B,
+    config: QDollarConfig,
+    verify: Verify,
+) -> Result<CompiledRecognizer, QDollarError>
+where
+    B: AsRef<[u8]> + Send + Sync + 'static,
+{
+    compiled::check_config(&config)?;
+    let all = bytes.as_ref();
+    let mut header = Reader::new(all);
+    if header.take(MAGIC.len())? != MAGIC {
+        return Err(corrupt("not a template file"));
+    }
+    let version = header.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(QDollarError::Serialization(format!(
+            "unsupported binary template format version {}",
+            version
+        )));
+    }
+    match header.u16()? {
+        BYTE_ORDER_MARK => {}
+        mark if mark == BYTE_ORDER_MARK.swap_bytes() => {
+            return Err(corrupt("written in the other byte order"));
+        }
+        _ => return Err(corrupt("bad byte order mark")),
+    }
+    let (lut_size, num_points) = (header.u16()?, header.u16()?);
+    if usize::from(lut_size) != LUT_SIZE || usize::from(num_points) != NUM_POINTS {
+        return Err(QDollarError::Serialization(format!(
+            "templates have {} points and {}x{} LUTs, expected {} and {}x{}",
+            num_points, lut_size, lut_size, NUM_POINTS, LUT_SIZE, LUT_SIZE
+        )));
+    }
+    let count = header.u32()? as usize;
+    let fingerprint = header.u32()?;
+    let table_len = header.usize()?;
+    let data_offset = header.usize()?;
+    let data_len = header.usize()?;
+    let header_checksum = header.u64()?;
+    let data_checksum = header.u64()?;
+
+    let points_len = count
+        .checked_mul(POINTS_LEN)
+        .ok_or_else(|| corrupt("too many templates"))?;
+    let luts_len = count
+        .checked_mul(LUT_LEN * 2)
+        .and_then(|len| len.checked_add(points_len))
+        .filter(|&len| len == data_len)
+        .ok_or_else(|| corrupt("data length does not match the template count"))?
+        - points_len;
+    let table_end = HEADER_LEN
+        .checked_add(table_len)
+        .filter(|&end| align(end) == data_offset)
+        .ok_or_else(|| corrupt("bad table length"))?;
+    if data_offset.checked_add(data_len) != Some(all.len()) {
+        return Err(corrupt("file length does not match the header"));
+    }
+
+    if checksum(&[&all[..CHECKSUMS_AT], &all[HEADER_LEN..data_offset]]) != header_checksum {
+        return Err(corrupt("header checksum mismatch"));
+    }
+    if verify == Verify::All && checksum(&[&all[data_offset..]]) != data_checksum {
+        return Err(corrupt("data checksum mismatch"));
+    }
+    if all[table_end..data_offset].iter().any(|&b| b != 0) {
+        return Err(corrupt("non-zero padding"));
+    }
+    if fingerprint != config_fingerprint(&config) {
+        return Err(QDollarError::ConfigMismatch);
+    }
+
+    let mut table = Reader::new(&all[HEADER_LEN..table_end]);
+    let mut templates = Vec::with_capacity(count.min(table_len / ENTRY_LEN));
+    for _ in 0..count {
+        templates.push(read_entry(&mut table)?);
+    }
+    if !table.is_empty() {
+        return Err(corrupt("trailing bytes in the template table"));
+    }
+
+    let points = data_offset..data_offset + points_len;
+    let luts = points.end..points.end + luts_len;
+    let snapshot = Snapshot::new(
+        config,
+        templates,
+        Arc::new(bytes),
+        points,
+        luts,
+        clock::default_clock(),
+    );
+    Ok(CompiledRecognizer::from_snapshot(snapshot))
+}
+
+fn read_entry(table: &mut Reader) -> Result<TemplateEntry, QDollarError> {
+    let stroke_count = table.u32()? as usize;
+    let name_len = table.u32()? as usize;
+    let metadata_len = table.u32()? as usize;
+    let mut features = Features::default();
+    for feature in &mut features.0 {
+        *feature = f64::from_le_bytes(table.array()?);
+    }
+    let name = core::str::from_utf8(table.take(name_len)?)
+        .map_err(|_| corrupt("template name is not UTF-8"))?
+        .to_string();
+    let metadata = match table.take(metadata_len)? {
+        [] => TemplateMetadata::default(),
+        json => {
+            serde_json::from_slice(json).map_err(|e| QDollarError::Serialization(e.to_string()))?
+        }
+    };
+    if !features.0.iter().all(|f| f.is_finite()) {
+        return Err(QDollarError::InvalidTemplate(format!(
+            "{} has non-finite features",
+            name
+        )));
+    }
+    Ok(TemplateEntry {
+        name,
+        metadata,
+        stroke_count,
+        features,
+    })
+}
+
+#[cfg(feature = "std")]
+pub fn save<P: AsRef<Path>>(path: P, compiled: &CompiledRecognizer) -> Result<(), QDollarError> {
+    fs::write(path, to_bytes(compiled)?).map_err(|e| QDollarError::Io(e.to_string()))
+}
+
+/// Reads the whole file into memory. `map` avoids the copy.
+#[cfg(feature = "std")]
+pub fn load<P: AsRef<Path>>(
+    path: P,
+    config: QDollarConfig,
+    verify: Verify,
+) -> Result<CompiledRecognizer, QDollarError> {
+    let bytes = fs::read(path).map_err(|e| QDollarError::Io(e.to_string()))?;
+    from_bytes(bytes, config, verify)
+}
+
+/// Maps the file into memory, so only the pages matching touches are read.
+/// The file must not change while the snapshot is alive: replace it by
+/// writing a new file and renaming it over the old one.
+#[cfg(all(feature = "mmap", unix))]
+pub fn map<P: AsRef<Path>>(
+    path: P,
+    config: QDollarConfig,
+    verify: Verify,
+) -> Result<CompiledRecognizer, QDollarError> {
+    from_bytes(mapped::Mmap::open(path.as_ref())?, config, verify)
+}
+
+#[cfg(all(feature = "mmap", unix))]
+mod mapped {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::{io, ptr, slice};
+
+    use crate::error::QDollarError;
+
+    // A read-only private mapping of a whole file.
+    pub struct Mmap {
+        ptr: *mut libc::c_void,
+        len: usize,
+    }
+
+    // The mapping is never written through and lives until drop.
+    unsafe impl Send for Mmap {}
+    unsafe impl Sync for Mmap {}
+
+    impl Mmap {
+        pub fn open(path: &Path) -> Result<Self, QDollarError> {
+            let io_error = |e: io::Error| QDollarError::Io(e.to_string());
+            let file = File::open(path).map_err(io_error)?;
+            let len = file.metadata().map_err(io_error)?.len() as usize;
+            if len == 0 {
+                // mmap rejects empty mappings.
+                return Err(QDollarError::Serialization("empty template file".into()));
+            }
+            // SAFETY: a fresh read-only mapping of an open file, checked for
+            // failure below.
+            let ptr = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    len,
+                    libc::PROT_READ,
+                    libc::MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(io_error(io::Error::last_os_error()));
+            }
+            Ok(Mmap { ptr, len })
+        }
+    }
+
+    impl AsRef<[u8]> for Mmap {
+        fn as_ref(&self) -> &[u8] {
+            // SAFETY: `ptr` maps `len` readable bytes until drop.
+            unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+        }
+    }
+
+    impl Drop for Mmap {
+        fn drop(&mut self) {
+            // SAFETY: unmaps exactly the mapping `open` created.
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+// Hash of the settings that decide how the templates were built and are read:
+// preprocessing, resampling and precision. Each setting is a tag byte followed
+// by its parameters.
+pub(crate) fn config_fingerprint(config: &QDollarConfig) -> u32 {
+    let mut bytes = Vec::new();
+    let mut put = |tag: u8, params: &[f64]| {
+        bytes.push(tag);
+        for param in params {
+            bytes.extend_from_slice(&param.to_le_bytes());
+        }
+    };
+    for step in &config.preprocessing {
+        match *step {
+            Preprocess::Dedupe { min_distance } => put(1, &[min_distance]),
+            Preprocess::MovingAverage { window } => put(2, &[window as f64]),
+            Preprocess::OneEuro {
+                min_cutoff,
+                beta,
+                d_cutoff,
+            } => put(3, &[min_cutoff, beta, d_cutoff]),
+            Preprocess::RemoveHooks {
+                max_fraction,
+                min_angle,
+            } => put(4, &[max_fraction, min_angle]),
+            Preprocess::MinStrokeLength { min_length } => put(5, &[min_length]),
+        }
+    }
+    match config.resample_strategy {
+        ResampleStrategy::Equidistant => put(16, &[]),
+        ResampleStrategy::EquidistantTime => put(17, &[]),
+        ResampleStrategy::CurvatureAdaptive { corner_angle } => put(18, &[corner_angle]),
+        ResampleStrategy::PerStroke => put(19, &[]),
+    }
+    match config.precision {
+        Precision::F64 => put(32, &[]),
+        Precision::F32 => put(33, &[]),
+        Precision::Grid => put(34, &[]),
+    }
+    let hash = checksum(&[&bytes]);
+    (hash ^ (hash >> 32)) as u32
+}
+
+// 64-bit FNV-1a over `parts` in order.
+pub(crate) fn checksum(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in parts.iter().copied().flatten() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn align(offset: usize) -> usize {
+    offset.div_ceil(8) * 8
+}
+
+fn put_u32(bytes: &mut Vec<u8>, value: usize) -> Result<(), QDollarError> {
+    let value = u32::try_from(value)
+        .map_err(|_| QDollarError::Serialization(format!("{} does not fit in 32 bits", value)))?;
+    bytes.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn corrupt(reason: &str) -> QDollarError {
+    QDollarError::Serialization(format!("corrupt template file: {}", reason))
+}
+
+// Reads little-endian numbers, failing instead of reading past the end.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], QDollarError> {
+        if len > self.bytes.len() {
+            return Err(corrupt("unexpected end of file"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], QDollarError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u16(&mut self) -> Result<u16, QDollarError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, QDollarError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, QDollarError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn usize(&mut self) -> Result<usize, QDollarError> {
+        usize::try_from(self.u64()?).map_err(|_| corrupt("length does not fit in memory"))
+    }
+}
@@ -0,0 +1,244 @@
+// The qdollar-capi package in capi/ builds this as a shared and a static C
+// library: `cargo build --release -p qdollar-capi`.
+
+use std::ffi::{c_char, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::error::QDollarError;
+use crate::{Point, QDollarRecognizer};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QDollarStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidString = 2,
+    BufferTooSmall = 3,
+    NoRegisteredGestures = 4,
+    Serialization = 5,
+    Io = 6,
+    InvalidTemplate = 7,
+    Unsupported = 8,
+    EmptyGesture = 9,
+    /// The call panicked. The recognizer may be left partly updated.
+    Panic = 10,
+    ConfigMismatch = 11,
+}
+
+impl From<&QDollarError> for QDollarStatus {
+    fn from(error: &QDollarError) -> Self {
+        match error {
+            QDollarError::NoRegisteredGestures => QDollarStatus::NoRegisteredGestures,
+            QDollarError::Serialization(_) => QDollarStatus::Serialization,
+            QDollarError::Io(_) => QDollarStatus::Io,
+            QDollarError::InvalidTemplate(_) => QDollarStatus::InvalidTemplate,
+            QDollarError::Unsupported(_) => QDollarStatus::Unsupported,
+            QDollarError::EmptyGesture => QDollarStatus::EmptyGesture,
+            QDollarError::ConfigMismatch => QDollarStatus::ConfigMismatch,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QDollarPoint {
+    pub x: f64,
+    pub y: f64,
+    pub stroke_id: u32,
+}
+
+/// Creates an empty recognizer, or returns NULL on failure. Free it with
+/// `qdollar_recognizer_free`.
+#[no_mangle]
+pub extern "C" fn qdollar_recognizer_new() -> *mut QDollarRecognizer {
+    panic::catch_unwind(|| Box::into_raw(Box::new(QDollarRecognizer::new())))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// # Safety
+/// `recognizer` must come from `qdollar_recognizer_new` and not be used
+/// afterwards. NULL is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn qdollar_recognizer_free(recognizer: *mut QDollarRecognizer) {
+    if !recognizer.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(recognizer))));
+    }
+}
+
+/// Adds a template. `name` is a NUL-terminated UTF-8 string and `points`
+/// holds `len` points, at least one. The new template count is written to
+/// `count` unless it is NULL.
+///
+/// # Safety
+/// All pointers must be valid for the given lengths.
+#[no_mangle]
+pub unsafe extern "C" fn qdollar_add_gesture(
+    recognizer: *mut QDollarRecognizer,
+    name: *const c_char,
+    points: *const QDollarPoint,
+    len: usize,
+    count: *mut usize,
+) -> QDollarStatus {
+    guard(|| {
+        let Some(recognizer) = recognizer.as_mut() else {
+            return QDollarStatus::NullPointer;
+        };
+        let name = match to_str(name) {
+            Ok(name) => name,
+            Err(status) => return status,
+        };
+        let points = match to_points(points, len) {
+            Ok(points) => points,
+            Err(status) => return status,
+        };
+
+        match recognizer.add_gesture(name.to_string(), points) {
+            Ok(n) => {
+                if !count.is_null() {
+                    *count = n;
+                }
+                QDollarStatus::Ok
+            }
+            Err(e) => QDollarStatus::from(&e),
+        }
+    })
+}
+
+/// Recognizes a gesture, writing the NUL-terminated name of the best match
+/// into `name` (`name_capacity` bytes) and its score into `score`.
+///
+/// # Safety
+/// All pointers must be valid for the given lengths.
+#[no_mangle]
+pub unsafe extern "C" fn qdollar_recognize(
+    recognizer: *const QDollarRecognizer,
+    points: *const QDollarPoint,
+    len: usize,
+    name: *mut c_char,
+    name_capacity: usize,
+    score: *mut f64,
+) -> QDollarStatus {
+    guard(|| {
+        let Some(recognizer) = recognizer.as_ref() else {
+            return QDollarStatus::NullPointer;
+        };
+        if name.is_null() || score.is_null() {
+            return QDollarStatus::NullPointer;
+        }
+        let points = match to_points(points, len) {
+            Ok(points) => points,
+            Err(status) => return status,
+        };
+
+        match recognizer.recognize(&points) {
+            Ok(result) => {
+                let bytes = result.name.as_bytes();
+                if bytes.len() >= name_capacity {
+                    return QDollarStatus::BufferTooSmall;
+                }
+                ptr::copy_nonoverlapping(bytes.as_ptr(), name as *mut u8, bytes.len());
+                *name.add(bytes.len()) = 0;
+                *score = result.score;
+                QDollarStatus::Ok
+            }
+            Err(e) => QDollarStatus::from(&e),
+        }
+    })
+}
+
+/// Saves the recognizer's templates as JSON.
+///
+/// # Safety
+/// `path` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn qdollar_save(
+    recognizer: *const QDollarRecognizer,
+    path: *const c_char,
+) -> QDollarStatus {
+    guard(|| {
+        let Some(recognizer) = recognizer.as_ref() else {
+            return QDollarStatus::NullPointer;
+        };
+        match to_str(path) {
+            Ok(path) => status(recognizer.save(path)),
+            Err(status) => status,
+        }
+    })
+}
+
+/// Replaces the recognizer's templates with the ones saved at `path`.
+///
+/// # Safety
+/// `path` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn qdollar_load(
+    recognizer: *mut QDollarRecognizer,
+    path: *const c_char,
+) -> QDollarStatus {
+    guard(|| {
+        let Some(recognizer) = recognizer.as_mut() else {
+            return QDollarStatus::NullPointer;
+        };
+        match to_str(path) {
+            Ok(path) => status(recognizer.load(path)),
+            Err(status) => status,
+        }
+    })
+}
+
+/// A static, NUL-terminated description of `status`.
+#[no_mangle]
+pub extern "C" fn qdollar_status_message(status: QDollarStatus) -> *const c_char {
+    let message: &'static [u8] = match status {
+        QDollarStatus::Ok => b"ok\0",
+        QDollarStatus::NullPointer => b"null pointer\0",
+        QDollarStatus::InvalidString => b"string is not valid UTF-8\0",
+        QDollarStatus::BufferTooSmall => b"buffer too small\0",
+        QDollarStatus::NoRegisteredGestures => b"no gestures registered for recognition\0",
+        QDollarStatus::Serialization => b"serialization failed\0",
+        QDollarStatus::Io => b"I/O error\0",
+        QDollarStatus::InvalidTemplate => b"invalid template\0",
+        QDollarStatus::Unsupported => b"unsupported configuration\0",
+        QDollarStatus::EmptyGesture => b"gesture has no points\0",
+        QDollarStatus::Panic => b"internal error\0",
+        QDollarStatus::ConfigMismatch => b"templates were built with a different configuration\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+// Runs an exported function's body, reporting a panic as a status instead of
+// unwinding into C, which aborts the process.
+fn guard<F: FnOnce() -> QDollarStatus>(body: F) -> QDollarStatus {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(QDollarStatus::Panic)
+}
+
+fn status<T>(result: Result<T, QDollarError>) -> QDollarStatus {
+    match result {
+        Ok(_) => QDollarStatus::Ok,
+        Err(e) => QDollarStatus::from(&e),
+    }
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, QDollarStatus> {
+    if s.is_null() {
+        return Err(QDollarStatus::NullPointer);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| QDollarStatus::InvalidString)
+}
+
+unsafe fn to_points(points: *const QDollarPoint, len: usize) -> Result<Vec<Point>, QDollarStatus> {
+    if len == 0 {
+        return Err(QDollarStatus::EmptyGesture);
+    }
+    if points.is_null() {
+        return Err(QDollarStatus::NullPointer);
+    }
+    Ok(slice::from_raw_parts(points, len)
+        .iter()
+        .map(|pt| Point::new(pt.x, pt.y, pt.stroke_id))
+        .collect())
+}
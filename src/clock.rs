@@ -0,0 +1,73 @@
+use alloc::sync::Arc;
+use core::time::Duration;
+
+/// Monotonic time source used to measure recognition time.
+///
+/// `std::time::Instant` panics on `wasm32-unknown-unknown`, so the recognizer
+/// reads time through this trait instead.
+pub trait Clock: Send + Sync {
+    /// Time elapsed since an arbitrary but fixed origin.
+    fn now(&self) -> Duration;
+}
+
+/// Clock that never advances. Every reported time is zero.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoClock;
+
+impl Clock for NoClock {
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+// `Instant` needs std and panics on `wasm32-unknown-unknown`.
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use instant::StdClock;
+
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod instant {
+    use core::time::Duration;
+    use std::time::Instant;
+
+    use super::Clock;
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct StdClock {
+        origin: Instant,
+    }
+
+    impl Default for StdClock {
+        fn default() -> Self {
+            StdClock {
+                origin: Instant::now(),
+            }
+        }
+    }
+
+    impl Clock for StdClock {
+        fn now(&self) -> Duration {
+            self.origin.elapsed()
+        }
+    }
+}
+
+/// `StdClock` where `Instant` works, otherwise `NoClock`. Without the `std`
+/// feature recognition is untimed unless a clock is set on the recognizer.
+pub fn default_clock() -> Arc<dyn Clock> {
+    #[cfg(all(
+        feature = "std",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    return Arc::new(StdClock::default());
+    #[cfg(not(all(
+        feature = "std",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    )))]
+    return Arc::new(NoClock);
+}
@@ -0,0 +1,499 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::binary::{self, Verify};
+use crate::clock::{self, Clock};
+use crate::distance;
+use crate::error::QDollarError;
+use crate::index::{Features, VpTree};
+use crate::metadata::{self, TemplateMetadata};
+use crate::qdollar::{self, QDollarResult, RecognizeStats, TemplateId};
+use crate::scalar::{Coords, Scalar};
+use crate::utils::{self, CloudView, LUT_SIZE, NUM_POINTS};
+use crate::{Matching, Point, PointCloud, Precision, Prefilter, QDollarConfig};
+
+/// Entries in one template's flattened LUT.
+pub const LUT_LEN: usize = LUT_SIZE * LUT_SIZE;
+
+/// An immutable set of templates laid out for matching, cheap to clone and
+/// share between threads. Clones share the templates, so a server can swap in
+/// a new snapshot from `CompiledRecognizerBuilder` while readers keep using
+/// the old one.
+///
+/// All templates' points sit in one array as 16-bit fixed point, at 1/65535
+/// of the gesture size, and their LUTs in another, each row-major with 16-bit
+/// point indices. Both are read in place as little-endian bytes, so a
+/// snapshot can live in a mapped `binary` file. Scores can differ from
+/// `QDollarRecognizer` by about 1e-4.
+/// Results report templates as `TemplateId::Base`, indexed in the order the
+/// builder holds them.
+#[derive(Clone)]
+pub struct CompiledRecognizer {
+    snapshot: Arc<Snapshot>,
+}
+
+/// Bytes a snapshot reads its points and LUTs from.
+pub(crate) type Bytes = Arc<dyn AsRef<[u8]> + Send + Sync>;
+
+pub(crate) struct Snapshot {
+    pub config: QDollarConfig,
+    pub templates: Vec<TemplateEntry>,
+    pub data: Bytes,
+    /// `utils::NUM_POINTS` points per template, each two u16 in `data`.
+    pub points: Range<usize>,
+    /// `LUT_LEN` u16 per template in `data`.
+    pub luts: Range<usize>,
+    index: VpTree,
+    clock: Arc<dyn Clock>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TemplateEntry {
+    pub name: String,
+    pub metadata: TemplateMetadata,
+    pub stroke_count: usize,
+    pub features: Features,
+}
+
+/// Collects templates and compiles them into `CompiledRecognizer` snapshots.
+/// Templates are normalized as they are added, so `build` only copies them
+/// into place.
+#[derive(Clone)]
+pub struct CompiledRecognizerBuilder {
+    config: QDollarConfig,
+    templates: Vec<PointCloud>,
+    clock: Arc<dyn Clock>,
+}
+
+impl CompiledRecognizerBuilder {
+    pub fn new(config: QDollarConfig) -> Self {
+        CompiledRecognizerBuilder {
+            config,
+            templates: Vec::new(),
+            clock: clock::default_clock(),
+        }
+    }
+
+    pub fn config(&self) -> &QDollarConfig {
+        &self.config
+    }
+
+    pub fn templates(&self) -> &[PointCloud] {
+        &self.templates
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn add_gesture(&mut self, name: String, points: Vec<Point>) -> &mut Self {
+        self.add_gesture_with_metadata(name, points, TemplateMetadata::default())
+    }
+
+    pub fn add_gesture_with_metadata(
+        &mut self,
+        name: String,
+        points: Vec<Point>,
+        metadata: TemplateMetadata,
+    ) -> &mut Self {
+        let cloud = PointCloud::with_config(name, points, &self.config).with_metadata(metadata);
+        self.add_template(cloud)
+    }
+
+    /// Adds an already normalized template, such as one of
+    /// `QDollarRecognizer::user_gestures`.
+    pub fn add_template(&mut self, template: PointCloud) -> &mut Self {
+        self.templates.push(template);
+        self
+    }
+
+    /// Removes the templates of `class` and of every class below it, and
+    /// returns how many there were.
+    pub fn remove_class(&mut self, class: &str) -> usize {
+        let before = self.templates.len();
+        self.templates
+            .retain(|template| !metadata::in_class(&template.name, class));
+        before - self.templates.len()
+    }
+
+    pub fn retain<F: FnMut(&PointCloud) -> bool>(&mut self, keep: F) -> &mut Self {
+        self.templates.retain(keep);
+        self
+    }
+
+    /// A snapshot of the current templates. Fails for configurations the
+    /// compiled layout cannot match, `Matching::Optimal` and stroke features,
+    /// and for templates without `utils::NUM_POINTS` points, such as those
+    /// normalized from an empty gesture.
+    pub fn build(&self) -> Result<CompiledRecognizer, QDollarError> {
+        check_config(&self.config)?;
+        let mut data = Vec::with_capacity(self.templates.len() * (POINTS_LEN + LUT_LEN * 2));
+        for template in &self.templates {
+            if template.points.len() != NUM_POINTS {
+                return Err(QDollarError::InvalidTemplate(format!(
+                    "{} has {} points, expected {}",
+                    template.name,
+                    template.points.len(),
+                    NUM_POINTS
+                )));
+            }
+            for pt in &template.points {
+                let [x, y] = encode_point(pt);
+                data.extend_from_slice(&x.to_le_bytes());
+                data.extend_from_slice(&y.to_le_bytes());
+            }
+        }
+        let points = 0..data.len();
+        for template in &self.templates {
+            for &i in template.lut.iter().flatten() {
+                data.extend_from_slice(&(i as u16).to_le_bytes());
+            }
+        }
+        let luts = points.end..data.len();
+
+        let templates = self
+            .templates
+            .iter()
+            .map(|template| TemplateEntry {
+                name: template.name.clone(),
+                metadata: template.metadata.clone(),
+                stroke_count: template.stroke_count(),
+                features: *template.features(),
+            })
+            .collect();
+        Ok(CompiledRecognizer::from_snapshot(Snapshot::new(
+            self.config.clone(),
+            templates,
+            Arc::new(data),
+            points,
+            luts,
+            self.clock.clone(),
+        )))
+    }
+}
+
+impl fmt::Debug for CompiledRecognizerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CompiledRecognizerBuilder")
+            .field("config", &self.config)
+            .field("templates", &self.templates.len())
+            .finish()
+    }
+}
+
+impl CompiledRecognizer {
+    pub fn builder(config: QDollarConfig) -> CompiledRecognizerBuilder {
+        CompiledRecognizerBuilder::new(config)
+    }
+
+    pub(crate) fn from_snapshot(snapshot: Snapshot) -> Self {
+        CompiledRecognizer {
+            snapshot: Arc::new(snapshot),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    /// The snapshot in the `binary` format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, QDollarError> {
+        binary::to_bytes(self)
+    }
+
+    /// Loads a snapshot from `binary::to_bytes` output without copying its
+    /// points or LUTs. `config` must be the one the templates were
+    /// normalized with, or loading fails with `QDollarError::ConfigMismatch`.
+    pub fn from_bytes<B>(
+        bytes: B,
+        config: QDollarConfig,
+        verify: Verify,
+    ) -> Result<Self, QDollarError>
+    where
+        B: AsRef<[u8]> + Send + Sync + 'static,
+    {
+        binary::from_bytes(bytes, config, verify)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), QDollarError> {
+        binary::save(path, self)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        config: QDollarConfig,
+        verify: Verify,
+    ) -> Result<Self, QDollarError> {
+        binary::load(path, config, verify)
+    }
+
+    /// Like `load`, mapping the file instead of reading it. See `binary::map`.
+    #[cfg(all(feature = "mmap", unix))]
+    pub fn map<P: AsRef<Path>>(
+        path: P,
+        config: QDollarConfig,
+        verify: Verify,
+    ) -> Result<Self, QDollarError> {
+        binary::map(path, config, verify)
+    }
+
+    pub fn config(&self) -> &QDollarConfig {
+        &self.snapshot.config
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshot.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshot.templates.is_empty()
+    }
+
+    /// Whether both share the same snapshot.
+    pub fn ptr_eq(&self, other: &CompiledRecognizer) -> bool {
+        Arc::ptr_eq(&self.snapshot, &other.snapshot)
+    }
+
+    pub fn recognize(&self, points: &[Point]) -> Result<QDollarResult, QDollarError> {
+        let mut results = self.recognize_n_best(points, 1)?;
+        Ok(results.remove(0))
+    }
+
+    // Best `n` matches with at most one result per gesture name, best first.
+    pub fn recognize_n_best(
+        &self,
+        points: &[Point],
+        n: usize,
+    ) -> Result<Vec<QDollarResult>, QDollarError> {
+        let snapshot = &*self.snapshot;
+        let start = snapshot.clock.now();
+        let candidate =
+            PointCloud::try_with_config(String::new(), points.to_vec(), &snapshot.config)?;
+        let normalization_time = snapshot.clock.now().saturating_sub(start);
+
+        let mut stats = RecognizeStats::default();
+        let mut matches = match snapshot.config.precision {
+            Precision::F64 => snapshot.best_matches::<f64>(&candidate, n.max(1), &mut stats),
+            Precision::F32 => snapshot.best_matches::<f32>(&candidate, n.max(1), &mut stats),
+            Precision::Grid => snapshot.best_matches::<i64>(&candidate, n.max(1), &mut stats),
+        };
+        if matches.is_empty() {
+            return Err(QDollarError::NoRegisteredGestures);
+        }
+        let time = snapshot.clock.now().saturating_sub(start);
+
+        stats.normalization_time = normalization_time;
+        stats.matching_time = time.saturating_sub(normalization_time);
+        matches.truncate(n);
+
+        Ok(matches
+            .into_iter()
+            .map(|(i, distance)| {
+                let template = &snapshot.templates[i];
+                QDollarResult {
+                    name: template.name.clone(),
+                    score: qdollar::score(distance),
+                    template: TemplateId::Base(i),
+                    metadata: template.metadata.clone(),
+                    time,
+                    stats: snapshot.config.collect_stats.then_some(stats),
+                }
+            })
+            .collect())
+    }
+}
+
+impl fmt::Debug for CompiledRecognizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CompiledRecognizer")
+            .field("config", &self.snapshot.config)
+            .field("templates", &self.snapshot.templates.len())
+            .finish()
+    }
+}
+
+// Bytes of one template's points in `Snapshot::data`.
+pub(crate) const POINTS_LEN: usize = NUM_POINTS * 4;
+
+pub(crate) fn check_config(config: &QDollarConfig) -> Result<(), QDollarError> {
+    if config.matching != Matching::Greedy {
+        return Err(QDollarError::Unsupported(format!(
+            "{:?} matching",
+            config.matching
+        )));
+    }
+    if config.stroke_features.is_enabled() {
+        return Err(QDollarError::Unsupported("stroke features".into()));
+    }
+    Ok(())
+}
+
+impl Snapshot {
+    // `points` and `luts` must hold `POINTS_LEN` and `LUT_LEN * 2` bytes for
+    // each of `templates`.
+    pub fn new(
+        config: QDollarConfig,
+        templates: Vec<TemplateEntry>,
+        data: Bytes,
+        points: Range<usize>,
+        luts: Range<usize>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        // Only prefiltering needs the tree, and building it for a large
+        // mapped file would cost much of the time mapping saves.
+        let index = match config.prefilter {
+            Prefilter::All => VpTree::default(),
+            Prefilter::Nearest { .. } => {
+                VpTree::new(templates.iter().map(|t| t.features).collect())
+            }
+        };
+        Snapshot {
+            config,
+            templates,
+            data,
+            points,
+            luts,
+            index,
+            clock,
+        }
+    }
+
+    // Same ranking as `QDollarRecognizer::best_matches`, over template
+    // indices.
+    fn best_matches<S: Scalar>(
+        &self,
+        candidate: &PointCloud,
+        n: usize,
+        stats: &mut RecognizeStats,
+    ) -> Vec<(usize, f64)> {
+        let candidate_coords = S::cloud_coords(candidate);
+        let candidate_view = CloudView {
+            points: &candidate.points,
+            coords: candidate_coords,
+            nearest: |pt: &Point| utils::lut_nearest(pt, &candidate.lut),
+        };
+        let order: Vec<usize> = match self.config.prefilter {
+            Prefilter::All => (0..self.templates.len()).collect(),
+            Prefilter::Nearest { k } => self
+                .index
+                .nearest(candidate.features(), k, |_| true)
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect(),
+        };
+
+        let data = (*self.data).as_ref();
+        let (all_points, all_luts) = (&data[self.points.clone()], &data[self.luts.clone()]);
+
+        // Reused for every template, so matching allocates nothing per
+        // template beyond what the matcher itself does.
+        let mut points = Vec::new();
+        let mut coords = Coords {
+            xs: Vec::new(),
+            ys: Vec::new(),
+        };
+        let mut best: Vec<(usize, f64)> = Vec::with_capacity(n + 1);
+        for i in order {
+            let template = &self.templates[i];
+            let threshold = if best.len() == n {
+                best[n - 1].1
+            } else {
+                f64::INFINITY
+            };
+            let calls = stats.counters.distance_calls;
+            let counters = &mut stats.counters;
+            let dist = distance::with_stroke_count(
+                &self.config.stroke_count,
+                candidate.stroke_count(),
+                template.stroke_count,
+                threshold,
+                |limit| {
+                    decode(
+                        &all_points[i * POINTS_LEN..(i + 1) * POINTS_LEN],
+                        &mut points,
+                        &mut coords,
+                    );
+                    let lut = &all_luts[i * LUT_LEN * 2..(i + 1) * LUT_LEN * 2];
+                    let view = CloudView {
+                        points: &points,
+                        coords: &coords,
+                        // A corrupt file can hold any index.
+                        nearest: |pt: &Point| {
+                            let (x, y) = utils::lut_cell(pt);
+                            let at = (x * LUT_SIZE + y) * 2;
+                            usize::from(read_u16(&lut[at..])).min(NUM_POINTS - 1)
+                        },
+                    };
+                    utils::match_views(&candidate_view, &view, limit, counters)
+                },
+            );
+            stats.templates += 1;
+            if stats.counters.distance_calls > calls {
+                stats.templates_evaluated += 1;
+            }
+            if dist >= threshold {
+                continue;
+            }
+            if let Some(j) = best
+                .iter()
+                .position(|&(t, _)| self.templates[t].name == template.name)
+            {
+                if dist >= best[j].1 {
+                    continue;
+                }
+                best.remove(j);
+            }
+            let at = best.partition_point(|&(_, d)| d <= dist);
+            best.insert(at, (i, dist));
+            best.truncate(n);
+        }
+
+        best
+    }
+}
+
+// Fills `points` and `coords` from the bytes of one template's points.
+fn decode<S: Scalar>(bytes: &[u8], points: &mut Vec<Point>, coords: &mut Coords<S>) {
+    points.clear();
+    points.extend(
+        bytes
+            .chunks_exact(4)
+            .map(|pt| decode_point([read_u16(pt), read_u16(&pt[2..])])),
+    );
+    coords.xs.clear();
+    coords.ys.clear();
+    for pt in points.iter() {
+        let [x, y] = S::coords(pt);
+        coords.xs.push(x);
+        coords.ys.push(y);
+    }
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+// Normalized coordinates lie in [-1, 1].
+fn encode_point(pt: &Point) -> [u16; 2] {
+    let encode = |v: f64| libm::round((v.clamp(-1.0, 1.0) + 1.0) / 2.0 * 65535.0) as u16;
+    [encode(pt.x), encode(pt.y)]
+}
+
+fn decode_point([x, y]: [u16; 2]) -> Point {
+    let decode = |v: u16| f64::from(v) / 65535.0 * 2.0 - 1.0;
+    let (x, y) = (decode(x), decode(y));
+    Point {
+        int_x: utils::int_coord(x),
+        int_y: utils::int_coord(y),
+        ..Point::new(x, y, 0)
+    }
+}
@@ -0,0 +1,244 @@
+use alloc::vec::Vec;
+use core::f64::consts::FRAC_PI_4;
+
+use crate::distance::QDollarDistance;
+use crate::preprocess;
+use crate::utils::{self, MatchCounters};
+use crate::{Point, PointCloud};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ResampleStrategy {
+    /// Points spaced evenly along the path, as in the original $Q.
+    #[default]
+    Equidistant,
+    /// Points spaced evenly in time. Needs `Point::time`, falls back to
+    /// `Equidistant` when the gesture has no timestamps.
+    EquidistantTime,
+    /// Keeps stroke endpoints and every vertex turning by at least
+    /// `corner_angle` radians, spreading the rest along the path.
+    CurvatureAdaptive { corner_angle: f64 },
+    /// Every stroke gets at least one point and the rest are shared out in
+    /// proportion to stroke length, so dots and short strokes survive.
+    PerStroke,
+}
+
+impl ResampleStrategy {
+    pub fn resample(&self, points: &[Point], n: usize) -> Vec<Point> {
+        match *self {
+            ResampleStrategy::Equidistant => utils::resample(points, n),
+            ResampleStrategy::EquidistantTime => utils::resample_by_time(points, n),
+            ResampleStrategy::CurvatureAdaptive { corner_angle } => {
+                utils::resample_curvature_adaptive(points, n, corner_angle)
+            }
+            ResampleStrategy::PerStroke => utils::resample_per_stroke(points, n),
+        }
+    }
+
+    pub fn curvature_adaptive() -> Self {
+        ResampleStrategy::CurvatureAdaptive {
+            corner_angle: FRAC_PI_4,
+        }
+    }
+}
+
+/// A cleanup step run on the raw points before resampling. Lengths are in
+/// the units of the input, typically pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preprocess {
+    /// Drops points no farther than `min_distance` from the last point kept
+    /// in their stroke. Zero drops exact repeats only.
+    Dedupe { min_distance: f64 },
+    /// Replaces each point with the mean of the `window` points of its stroke
+    /// centred on it.
+    MovingAverage { window: usize },
+    /// The One Euro filter of Casiez et al.: little smoothing while the pen
+    /// moves fast, more as it slows down. Cutoffs are frequencies in cycles
+    /// per unit of `Point::time`. Points without increasing timestamps are
+    /// taken to be one unit apart.
+    OneEuro {
+        min_cutoff: f64,
+        beta: f64,
+        d_cutoff: f64,
+    },
+    /// Cuts a stroke at its sharpest turn of at least `min_angle` radians
+    /// within `max_fraction` of its length from either end, removing the
+    /// hooks pens leave on touch-down and lift-off.
+    RemoveHooks { max_fraction: f64, min_angle: f64 },
+    /// Drops strokes shorter than `min_length`, such as accidental taps. The
+    /// longest stroke is always kept.
+    MinStrokeLength { min_length: f64 },
+}
+
+impl Preprocess {
+    pub fn apply(&self, points: &[Point]) -> Vec<Point> {
+        match *self {
+            Preprocess::Dedupe { min_distance } => preprocess::dedupe(points, min_distance),
+            Preprocess::MovingAverage { window } => preprocess::moving_average(points, window),
+            Preprocess::OneEuro {
+                min_cutoff,
+                beta,
+                d_cutoff,
+            } => preprocess::one_euro(points, min_cutoff, beta, d_cutoff),
+            Preprocess::RemoveHooks {
+                max_fraction,
+                min_angle,
+            } => preprocess::remove_hooks(points, max_fraction, min_angle),
+            Preprocess::MinStrokeLength { min_length } => {
+                preprocess::min_stroke_length(points, min_length)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LearningPolicy {
+    /// Every learned sample becomes a new user template.
+    #[default]
+    Append,
+    /// On a misrecognition, the learned sample replaces the user template of
+    /// the wrong class that won the match, so the template that caused the
+    /// confusion goes and the correct class keeps all of its templates. The
+    /// sample is appended instead when a base template won, as base templates
+    /// are shared and never replaced, or when the winner is the last template
+    /// of its class. Correct recognitions change nothing.
+    ReplaceMostConfused,
+    /// Append, then evict the least useful user template of the class once
+    /// it has more than `max_templates`.
+    CapPerClass { max_templates: usize },
+}
+
+/// Number type the recognizer computes distances in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precision {
+    #[default]
+    F64,
+    /// Distances differ from `F64` by rounding only.
+    F32,
+    /// Integer arithmetic on the `int_x`/`int_y` grid, for devices without
+    /// an FPU. Coordinates are quantized to 1/1023 of the gesture size.
+    Grid,
+}
+
+impl Precision {
+    pub fn cloud_match(
+        &self,
+        candidate: &PointCloud,
+        template: &PointCloud,
+        min_so_far: f64,
+        counters: &mut MatchCounters,
+    ) -> f64 {
+        match self {
+            Precision::F64 => {
+                utils::cloud_match_as::<f64>(candidate, template, min_so_far, counters)
+            }
+            Precision::F32 => {
+                utils::cloud_match_as::<f32>(candidate, template, min_so_far, counters)
+            }
+            Precision::Grid => {
+                utils::cloud_match_as::<i64>(candidate, template, min_so_far, counters)
+            }
+        }
+    }
+}
+
+/// How points of the two clouds are paired up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Matching {
+    /// The weighted greedy matching of $Q, with lower bounds and early
+    /// abandoning.
+    #[default]
+    Greedy,
+    /// The minimum-cost bijection, found with the Hungarian method. Much
+    /// slower and ignores `precision`, but gives the accuracy greedy matching
+    /// is an approximation of. `explain` still shows the greedy alignment.
+    Optimal,
+}
+
+/// How a difference in stroke count between candidate and template counts.
+///
+/// Strokes are counted on the preprocessed input, before resampling, so short
+/// strokes that resampling drops still count.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StrokeCount {
+    #[default]
+    Ignore,
+    /// Adds `per_stroke` to the distance for every stroke of difference.
+    Penalty { per_stroke: f64 },
+    /// Rejects templates whose stroke count differs by more than
+    /// `max_difference`.
+    Filter { max_difference: usize },
+}
+
+/// Weights of per-point stroke features added to the squared distance
+/// between matched points. With both at zero, the default, matching uses
+/// positions only.
+///
+/// Squared distances between points of a normalized cloud are mostly below
+/// 1, so weights around 0.1 make stroke structure a tie-breaker and weights
+/// near 1 make it as important as shape. Non-zero weights make `Greedy`
+/// matching ignore `precision`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StrokeFeatures {
+    /// Weight of the squared difference between the unit directions of
+    /// travel at the two points.
+    pub direction: f64,
+    /// Weight of the squared difference between the positions of the two
+    /// points' strokes in drawing order, scaled to [0, 1].
+    pub order: f64,
+}
+
+impl StrokeFeatures {
+    pub fn is_enabled(&self) -> bool {
+        self.direction != 0.0 || self.order != 0.0
+    }
+}
+
+/// Which templates get the full $Q match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Prefilter {
+    /// Every template, in order.
+    #[default]
+    All,
+    /// Only the `k` templates closest in `index::Features` space, closest
+    /// first. Base templates are found through a VP-tree, so the cost of
+    /// recognition stops growing with the number of templates.
+    Nearest { k: usize },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QDollarConfig {
+    /// Run in order on templates and candidates alike, before resampling.
+    pub preprocessing: Vec<Preprocess>,
+    pub resample_strategy: ResampleStrategy,
+    pub learning_policy: LearningPolicy,
+    /// Hide base templates of every class that has user templates.
+    pub user_shadows_base: bool,
+    /// Report pruning counters and timings in `QDollarResult::stats`.
+    pub collect_stats: bool,
+    pub precision: Precision,
+    pub prefilter: Prefilter,
+    pub matching: Matching,
+    pub stroke_count: StrokeCount,
+    pub stroke_features: StrokeFeatures,
+}
+
+impl QDollarConfig {
+    /// `points` after every step of `preprocessing`.
+    pub fn preprocess(&self, points: &[Point]) -> Vec<Point> {
+        let mut points = points.to_vec();
+        for step in &self.preprocessing {
+            points = step.apply(&points);
+        }
+        points
+    }
+
+    /// The cloud distance `matching` and `precision` select.
+    pub fn distance(&self) -> QDollarDistance {
+        QDollarDistance {
+            matching: self.matching,
+            precision: self.precision,
+            stroke_count: self.stroke_count,
+            stroke_features: self.stroke_features,
+        }
+    }
+}
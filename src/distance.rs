@@ -0,0 +1,244 @@
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+use crate::config::{StrokeCount, StrokeFeatures};
+use crate::scalar::sqr_distance;
+use crate::utils::{self, MatchCounters};
+use crate::{Matching, PointCloud, Precision};
+
+/// Dissimilarity between two normalized point clouds.
+///
+/// The recognizer picks the template with the smallest distance, so only the
+/// order matters, but scores assume distances of about 1 are poor matches.
+pub trait CloudDistance: Send + Sync {
+    /// Distance from `candidate` to `template`. Once it is known to be at
+    /// least `min_so_far` the computation may stop and return any value not
+    /// below `min_so_far`.
+    fn distance(
+        &self,
+        candidate: &PointCloud,
+        template: &PointCloud,
+        min_so_far: f64,
+        counters: &mut MatchCounters,
+    ) -> f64;
+}
+
+/// The $Q matching distance, as selected by the `QDollarConfig` fields of
+/// the same names. The recognizer's default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QDollarDistance {
+    pub matching: Matching,
+    pub precision: Precision,
+    pub stroke_count: StrokeCount,
+    pub stroke_features: StrokeFeatures,
+}
+
+impl QDollarDistance {
+    // The points' distance without the stroke count penalty.
+    fn point_distance(
+        &self,
+        candidate: &PointCloud,
+        template: &PointCloud,
+        min_so_far: f64,
+        counters: &mut MatchCounters,
+    ) -> f64 {
+        match self.matching {
+            Matching::Greedy if self.stroke_features.is_enabled() => utils::stroke_match(
+                candidate,
+                template,
+                &self.stroke_features,
+                min_so_far,
+                counters,
+            ),
+            Matching::Greedy => self
+                .precision
+                .cloud_match(candidate, template, min_so_far, counters),
+            Matching::Optimal => utils::optimal_match(candidate, template, min_so_far, counters),
+        }
+    }
+}
+
+impl CloudDistance for QDollarDistance {
+    fn distance(
+        &self,
+        candidate: &PointCloud,
+        template: &PointCloud,
+        min_so_far: f64,
+        counters: &mut MatchCounters,
+    ) -> f64 {
+        with_stroke_count(
+            &self.stroke_count,
+            candidate.stroke_count(),
+            template.stroke_count(),
+            min_so_far,
+            |limit| self.point_distance(candidate, template, limit, counters),
+        )
+    }
+}
+
+// `point_distance`, which takes the limit to abandon at, plus the `stroke_count`
+// penalty for clouds of `strokes1` and `strokes2` strokes.
+pub(crate) fn with_stroke_count(
+    stroke_count: &StrokeCount,
+    strokes1: usize,
+    strokes2: usize,
+    min_so_far: f64,
+    point_distance: impl FnOnce(f64) -> f64,
+) -> f64 {
+    let difference = strokes1.abs_diff(strokes2);
+    let penalty = match *stroke_count {
+        StrokeCount::Ignore => 0.0,
+        StrokeCount::Penalty { per_stroke } => per_stroke * difference as f64,
+        StrokeCount::Filter { max_difference } if difference > max_difference => {
+            return f64::INFINITY;
+        }
+        StrokeCount::Filter { .. } => 0.0,
+    };
+    if penalty == 0.0 {
+        return point_distance(min_so_far);
+    }
+    if penalty >= min_so_far {
+        return min_so_far;
+    }
+
+    let limit = min_so_far - penalty;
+    let dist = point_distance(limit);
+    if dist >= limit {
+        min_so_far
+    } else {
+        dist + penalty
+    }
+}
+
+/// Modified Hausdorff distance of Dubuisson and Jain: the larger of the two
+/// mean distances from a point of one cloud to the nearest of the other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModifiedHausdorff;
+
+impl CloudDistance for ModifiedHausdorff {
+    fn distance(
+        &self,
+        candidate: &PointCloud,
+        template: &PointCloud,
+        _min_so_far: f64,
+        counters: &mut MatchCounters,
+    ) -> f64 {
+        counters.distance_calls += 1;
+        let directed = |from: &PointCloud, to: &PointCloud| {
+            let coords = to.coords();
+            mean(from.points.iter().map(|pt| {
+                let pt = [pt.x, pt.y];
+                (0..coords.len())
+                    .map(|j| libm::sqrt(sqr_distance(pt, coords.get(j))))
+                    .fold(f64::INFINITY, f64::min)
+            }))
+        };
+        directed(candidate, template).max(directed(template, candidate))
+    }
+}
+
+/// Symmetric Chamfer distance: the mean squared distance from each point to
+/// the nearest point of the other cloud, summed over both directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chamfer {
+    /// Find nearest points through each cloud's `lut` instead of a search.
+    /// The lookup is only as fine as the LUT grid, so the distance can come
+    /// out slightly larger, but it costs one table read per point.
+    pub use_lut: bool,
+}
+
+impl Default for Chamfer {
+    fn default() -> Self {
+        Chamfer { use_lut: true }
+    }
+}
+
+impl CloudDistance for Chamfer {
+    fn distance(
+        &self,
+        candidate: &PointCloud,
+        template: &PointCloud,
+        _min_so_far: f64,
+        counters: &mut MatchCounters,
+    ) -> f64 {
+        counters.distance_calls += 1;
+        let directed = |from: &PointCloud, to: &PointCloud| {
+            let coords = to.coords();
+            mean(from.points.iter().map(|pt| {
+                let pt_coords = [pt.x, pt.y];
+                if self.use_lut {
+                    sqr_distance(pt_coords, coords.get(utils::lut_nearest(pt, &to.lut)))
+                } else {
+                    (0..coords.len())
+                        .map(|j| sqr_distance(pt_coords, coords.get(j)))
+                        .fold(f64::INFINITY, f64::min)
+                }
+            }))
+        };
+        directed(candidate, template) + directed(template, candidate)
+    }
+}
+
+/// Sliced approximation of the Earth Mover's distance between the clouds as
+/// uniform distributions: the mean over `projections` evenly spread
+/// directions of the 1D transport cost, which sorting solves exactly. For
+/// clouds of equal size it never exceeds the exact distance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApproximateEmd {
+    pub projections: usize,
+}
+
+impl Default for ApproximateEmd {
+    fn default() -> Self {
+        ApproximateEmd { projections: 16 }
+    }
+}
+
+impl CloudDistance for ApproximateEmd {
+    fn distance(
+        &self,
+        candidate: &PointCloud,
+        template: &PointCloud,
+        _min_so_far: f64,
+        counters: &mut MatchCounters,
+    ) -> f64 {
+        counters.distance_calls += 1;
+        let n = candidate.points.len().min(template.points.len());
+        if n == 0 || self.projections == 0 {
+            return 0.0;
+        }
+
+        let projected = |cloud: &PointCloud, (sin, cos): (f64, f64)| {
+            let mut values: Vec<f64> = cloud
+                .points
+                .iter()
+                .map(|pt| pt.x * cos + pt.y * sin)
+                .collect();
+            values.sort_unstable_by(f64::total_cmp);
+            values
+        };
+        let total: f64 = (0..self.projections)
+            .map(|k| {
+                let direction = libm::sincos(PI * k as f64 / self.projections as f64);
+                let a = projected(candidate, direction);
+                let b = projected(template, direction);
+                // Quantiles, so clouds of different sizes still line up.
+                mean((0..n).map(|i| {
+                    let ia = i * a.len() / n;
+                    let ib = i * b.len() / n;
+                    (a[ia] - b[ib]).abs()
+                }))
+            })
+            .sum();
+        total / self.projections as f64
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
@@ -1,17 +1,36 @@
-use std::error::Error;
-use std::fmt;
+use alloc::string::String;
+use core::fmt;
 
 #[derive(Debug, PartialEq)]
 pub enum QDollarError {
     NoRegisteredGestures,
+    /// The gesture has no points.
+    EmptyGesture,
+    Serialization(String),
+    Io(String),
+    InvalidTemplate(String),
+    /// The operation does not support part of the configuration.
+    Unsupported(String),
+    /// Stored templates were built with a configuration that normalizes
+    /// gestures differently from the one given.
+    ConfigMismatch,
 }
 
 impl fmt::Display for QDollarError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             QDollarError::NoRegisteredGestures => write!(f, "No gestures registered for recognition"),
+            QDollarError::EmptyGesture => write!(f, "Gesture has no points"),
+            QDollarError::Serialization(e) => write!(f, "Serialization failed: {}", e),
+            QDollarError::Io(e) => write!(f, "I/O error: {}", e),
+            QDollarError::InvalidTemplate(e) => write!(f, "Invalid template: {}", e),
+            QDollarError::Unsupported(e) => write!(f, "Unsupported: {}", e),
+            QDollarError::ConfigMismatch => {
+                write!(f, "Templates were built with a different configuration")
+            }
         }
     }
 }
 
-impl Error for QDollarError {}
+#[cfg(feature = "std")]
+impl std::error::Error for QDollarError {}
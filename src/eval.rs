@@ -0,0 +1,194 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::rng::{self, SplitMix64};
+use crate::synth::Synthesizer;
+use crate::{Matching, Point, QDollarConfig, QDollarRecognizer, ResampleStrategy};
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub name: String,
+    pub points: Vec<Point>,
+}
+
+impl Sample {
+    pub fn new(name: String, points: Vec<Point>) -> Self {
+        Sample { name, points }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalReport {
+    pub correct: usize,
+    pub total: usize,
+    pub accuracy: f64,
+    pub mean_time: Duration,
+    /// Seed of the random choices behind the report, if any. Running the
+    /// same evaluation with it reproduces the report exactly.
+    pub seed: Option<u64>,
+    /// Indices of the templates left out because they have no points. For
+    /// `evaluate_split` they index the templates `split` picks.
+    pub skipped_templates: Vec<usize>,
+}
+
+pub fn evaluate(recognizer: &QDollarRecognizer, samples: &[Sample]) -> EvalReport {
+    let mut correct = 0;
+    let mut total_time = Duration::ZERO;
+
+    for sample in samples {
+        if let Ok(result) = recognizer.recognize(&sample.points) {
+            total_time += result.time;
+            if result.name == sample.name {
+                correct += 1;
+            }
+        }
+    }
+
+    let total = samples.len();
+    EvalReport {
+        correct,
+        total,
+        accuracy: if total > 0 {
+            correct as f64 / total as f64
+        } else {
+            0.0
+        },
+        mean_time: if total > 0 {
+            total_time / total as u32
+        } else {
+            Duration::ZERO
+        },
+        seed: None,
+        skipped_templates: Vec::new(),
+    }
+}
+
+pub fn evaluate_config(
+    config: &QDollarConfig,
+    templates: &[Sample],
+    samples: &[Sample],
+) -> EvalReport {
+    let mut recognizer = QDollarRecognizer::with_config(config.clone());
+    let mut skipped_templates = Vec::new();
+    for (i, template) in templates.iter().enumerate() {
+        if recognizer
+            .add_gesture(template.name.clone(), template.points.clone())
+            .is_err()
+        {
+            skipped_templates.push(i);
+        }
+    }
+    EvalReport {
+        skipped_templates,
+        ..evaluate(&recognizer, samples)
+    }
+}
+
+/// Like `evaluate_config`, with `per_template` synthetic variants of each
+/// template added next to it.
+pub fn evaluate_synthesized(
+    config: &QDollarConfig,
+    templates: &[Sample],
+    samples: &[Sample],
+    synthesizer: &Synthesizer,
+    per_template: usize,
+) -> EvalReport {
+    let mut recognizer = QDollarRecognizer::with_config(config.clone());
+    let mut skipped_templates = Vec::new();
+    for (i, template) in templates.iter().enumerate() {
+        // A different seed per template, so they are not all distorted alike.
+        let synthesizer = Synthesizer {
+            seed: synthesizer.seed.wrapping_add(i as u64),
+            ..synthesizer.clone()
+        };
+        let added = recognizer
+            .add_gesture(template.name.clone(), template.points.clone())
+            .and_then(|_| {
+                recognizer.add_synthetic_gestures(
+                    template.name.clone(),
+                    &template.points,
+                    per_template,
+                    &synthesizer,
+                )
+            });
+        if added.is_err() {
+            skipped_templates.push(i);
+        }
+    }
+    EvalReport {
+        seed: Some(synthesizer.seed),
+        skipped_templates,
+        ..evaluate(&recognizer, samples)
+    }
+}
+
+/// Shuffles `samples` with `seed` and splits them into templates, the first
+/// `per_class` of each class, and test samples, the rest.
+pub fn split(samples: &[Sample], per_class: usize, seed: u64) -> (Vec<Sample>, Vec<Sample>) {
+    let mut shuffled = samples.to_vec();
+    rng::shuffle(&mut shuffled, &mut SplitMix64::new(seed));
+
+    let mut taken: BTreeMap<&str, usize> = BTreeMap::new();
+    let (mut templates, mut tests) = (Vec::new(), Vec::new());
+    for sample in &shuffled {
+        let count = taken.entry(&sample.name).or_default();
+        if *count < per_class {
+            *count += 1;
+            templates.push(sample.clone());
+        } else {
+            tests.push(sample.clone());
+        }
+    }
+    (templates, tests)
+}
+
+/// Evaluates `config` on a random `split` of `samples`.
+pub fn evaluate_split(
+    config: &QDollarConfig,
+    samples: &[Sample],
+    per_class: usize,
+    seed: u64,
+) -> EvalReport {
+    let (templates, tests) = split(samples, per_class, seed);
+    EvalReport {
+        seed: Some(seed),
+        ..evaluate_config(config, &templates, &tests)
+    }
+}
+
+pub fn compare_strategies(
+    config: &QDollarConfig,
+    strategies: &[ResampleStrategy],
+    templates: &[Sample],
+    samples: &[Sample],
+) -> Vec<(ResampleStrategy, EvalReport)> {
+    strategies
+        .iter()
+        .map(|&resample_strategy| {
+            let mut config = config.clone();
+            config.resample_strategy = resample_strategy;
+            (
+                resample_strategy,
+                evaluate_config(&config, templates, samples),
+            )
+        })
+        .collect()
+}
+
+pub fn compare_matching(
+    config: &QDollarConfig,
+    matchings: &[Matching],
+    templates: &[Sample],
+    samples: &[Sample],
+) -> Vec<(Matching, EvalReport)> {
+    matchings
+        .iter()
+        .map(|&matching| {
+            let mut config = config.clone();
+            config.matching = matching;
+            (matching, evaluate_config(&config, templates, samples))
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::utils::{self, MatchCounters};
+use crate::{Point, PointCloud};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchedPair {
+    pub candidate_index: usize,
+    pub template_index: usize,
+    /// The $Q weight, from `n` for the first matched point down to 1.
+    pub weight: usize,
+    pub distance: f64,
+    /// `weight` times the squared distance, the pair's share of the total.
+    pub contribution: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchExplanation {
+    pub distance: f64,
+    pub start: usize,
+    /// Whether the template points were matched to the candidate rather
+    /// than the other way round.
+    pub reversed: bool,
+    pub pairs: Vec<MatchedPair>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateStatus {
+    /// Every start index was skipped by the lower bound.
+    Pruned,
+    /// Evaluated, but every `cloud_distance` call stopped early.
+    Abandoned,
+    /// Fully evaluated and the best match at the time.
+    Evaluated,
+    /// Left out by `QDollarConfig::prefilter`.
+    Filtered,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateEvaluation {
+    pub name: String,
+    pub status: TemplateStatus,
+    pub distance: Option<f64>,
+    pub counters: MatchCounters,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub name: String,
+    pub score: f64,
+    /// Index of the best template in `templates`.
+    pub best_template: usize,
+    pub alignment: MatchExplanation,
+    pub templates: Vec<TemplateEvaluation>,
+}
+
+// Runs every start index in both directions without pruning and returns the
+// matching behind the best one.
+pub fn explain_match(candidate: &PointCloud, template: &PointCloud) -> MatchExplanation {
+    let n = candidate.points.len();
+    let step = (libm::floor(libm::sqrt(n as f64)) as usize).max(1);
+
+    let mut best = (f64::INFINITY, false, 0);
+    for start in (0..n).step_by(step) {
+        for reversed in [false, true] {
+            let (pts1, pts2) = oriented(candidate, template, reversed);
+            let dist = utils::greedy_match(pts1, pts2, start, best.0, |_, _, _, _| {});
+            if dist < best.0 {
+                best = (dist, reversed, start);
+            }
+        }
+    }
+
+    let (distance, reversed, start) = best;
+    let (pts1, pts2) = oriented(candidate, template, reversed);
+    let mut pairs = Vec::with_capacity(n);
+    utils::greedy_match(
+        pts1,
+        pts2,
+        start,
+        f64::INFINITY,
+        |i, j, weight, sqr_dist| {
+            let (candidate_index, template_index) = if reversed { (j, i) } else { (i, j) };
+            pairs.push(MatchedPair {
+                candidate_index,
+                template_index,
+                weight,
+                distance: libm::sqrt(sqr_dist),
+                contribution: weight as f64 * sqr_dist,
+            });
+        },
+    );
+
+    MatchExplanation {
+        distance,
+        start,
+        reversed,
+        pairs,
+    }
+}
+
+fn oriented<'a>(
+    candidate: &'a PointCloud,
+    template: &'a PointCloud,
+    reversed: bool,
+) -> (&'a [Point], &'a [Point]) {
+    if reversed {
+        (&template.points, &candidate.points)
+    } else {
+        (&candidate.points, &template.points)
+    }
+}
@@ -0,0 +1,212 @@
+use alloc::vec::Vec;
+
+use crate::{utils, Point, PointCloud};
+
+pub const NUM_FEATURES: usize = 6;
+
+/// Cheap global shape descriptors of a normalized cloud, used to shortlist
+/// templates before running the full $Q match.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Features(pub [f64; NUM_FEATURES]);
+
+impl Features {
+    /// `points` must already be scaled and centred, as in a `PointCloud`.
+    ///
+    /// The features are the bounding box width over width plus height, the
+    /// stroke count, the path length relative to the bounding box and the
+    /// three second moments about the centroid, each scaled to roughly [0, 1].
+    pub fn new(points: &[Point]) -> Self {
+        if points.is_empty() {
+            return Features::default();
+        }
+
+        let (min_x, max_x, min_y, max_y) = points.iter().fold(
+            (
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(min_x, max_x, min_y, max_y), pt| {
+                (
+                    min_x.min(pt.x),
+                    max_x.max(pt.x),
+                    min_y.min(pt.y),
+                    max_y.max(pt.y),
+                )
+            },
+        );
+        let extent = (max_x - min_x) + (max_y - min_y) + 1e-6;
+        let strokes = utils::stroke_count(points);
+        let n = points.len() as f64;
+        let (mxx, myy, mxy) = points.iter().fold((0.0, 0.0, 0.0), |(xx, yy, xy), pt| {
+            (xx + pt.x * pt.x, yy + pt.y * pt.y, xy + pt.x * pt.y)
+        });
+
+        Features([
+            (max_x - min_x) / extent,
+            0.25 * strokes as f64,
+            0.25 * utils::path_length(points) / extent,
+            4.0 * mxx / n,
+            4.0 * myy / n,
+            4.0 * mxy / n,
+        ])
+    }
+
+    pub fn distance(&self, other: &Features) -> f64 {
+        let sum: f64 = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        libm::sqrt(sum)
+    }
+}
+
+/// Vantage-point tree over template features, for k-nearest-neighbour
+/// queries that visit a logarithmic number of templates on typical sets.
+#[derive(Debug, Clone, Default)]
+pub struct VpTree {
+    features: Vec<Features>,
+    nodes: Vec<VpNode>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct VpNode {
+    item: usize,
+    /// Items under `inside` are at most this far from `item`, items under
+    /// `outside` at least this far.
+    radius: f64,
+    inside: Option<usize>,
+    outside: Option<usize>,
+}
+
+impl VpTree {
+    pub fn new(features: Vec<Features>) -> Self {
+        let mut items: Vec<usize> = (0..features.len()).collect();
+        let mut nodes = Vec::with_capacity(features.len());
+        build(&mut nodes, &mut items, &features);
+        VpTree { features, nodes }
+    }
+
+    pub fn from_clouds(clouds: &[PointCloud]) -> Self {
+        Self::new(clouds.iter().map(|cloud| *cloud.features()).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    /// Up to `k` items accepted by `filter`, nearest to `query` first, with
+    /// their feature distances.
+    pub fn nearest<F>(&self, query: &Features, k: usize, filter: F) -> Vec<(usize, f64)>
+    where
+        F: Fn(usize) -> bool,
+    {
+        self.nearest_counted(query, k, filter, &mut 0)
+    }
+
+    /// Like `nearest`, adding the number of feature distances it computed to
+    /// `evaluations`.
+    pub fn nearest_counted<F>(
+        &self,
+        query: &Features,
+        k: usize,
+        filter: F,
+        evaluations: &mut usize,
+    ) -> Vec<(usize, f64)>
+    where
+        F: Fn(usize) -> bool,
+    {
+        let mut best = Vec::with_capacity(k + 1);
+        if k > 0 && !self.nodes.is_empty() {
+            self.search(0, query, k, &filter, &mut best, evaluations);
+        }
+        best
+    }
+
+    fn search<F>(
+        &self,
+        node: usize,
+        query: &Features,
+        k: usize,
+        filter: &F,
+        best: &mut Vec<(usize, f64)>,
+        evaluations: &mut usize,
+    ) where
+        F: Fn(usize) -> bool,
+    {
+        let VpNode {
+            item,
+            radius,
+            inside,
+            outside,
+        } = self.nodes[node];
+        let d = query.distance(&self.features[item]);
+        *evaluations += 1;
+        if filter(item) {
+            let at = best.partition_point(|&(i, dist)| (dist, i) <= (d, item));
+            if at < k {
+                best.insert(at, (item, d));
+                best.truncate(k);
+            }
+        }
+
+        let tau = |best: &Vec<(usize, f64)>| {
+            if best.len() == k {
+                best[k - 1].1
+            } else {
+                f64::INFINITY
+            }
+        };
+        let (first, second) = if d <= radius {
+            (inside, outside)
+        } else {
+            (outside, inside)
+        };
+        for (child, is_inside) in [(first, d <= radius), (second, d > radius)] {
+            let Some(child) = child else { continue };
+            let reachable = if is_inside {
+                d - tau(best) <= radius
+            } else {
+                d + tau(best) >= radius
+            };
+            if reachable {
+                self.search(child, query, k, filter, best, evaluations);
+            }
+        }
+    }
+}
+
+fn build(nodes: &mut Vec<VpNode>, items: &mut [usize], features: &[Features]) -> Option<usize> {
+    let (&mut vantage, rest) = items.split_first_mut()?;
+    let node = nodes.len();
+    nodes.push(VpNode {
+        item: vantage,
+        radius: 0.0,
+        inside: None,
+        outside: None,
+    });
+    if rest.is_empty() {
+        return Some(node);
+    }
+
+    let origin = features[vantage];
+    let mid = rest.len() / 2;
+    rest.select_nth_unstable_by(mid, |&a, &b| {
+        origin
+            .distance(&features[a])
+            .total_cmp(&origin.distance(&features[b]))
+    });
+    let radius = origin.distance(&features[rest[mid]]);
+    let (inside, outside) = rest.split_at_mut(mid);
+    nodes[node].radius = radius;
+    nodes[node].inside = build(nodes, inside, features);
+    nodes[node].outside = build(nodes, outside, features);
+    Some(node)
+}
@@ -1,16 +1,57 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod analysis;
+pub mod assignment;
+pub mod binary;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod clock;
+pub mod compiled;
+pub mod config;
+pub mod distance;
+pub mod eval;
+pub mod explain;
+pub mod index;
+pub mod metadata;
 pub mod point;
 pub mod point_cloud;
+pub mod preprocess;
+pub mod prune;
 pub mod qdollar;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod rng;
+pub mod scalar;
+#[cfg(feature = "simd")]
+mod simd;
+pub mod storage;
+pub mod synth;
 pub mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 pub mod error;
 
+pub use config::{
+    LearningPolicy, Matching, Precision, Prefilter, Preprocess, QDollarConfig, ResampleStrategy,
+    StrokeCount, StrokeFeatures,
+};
 pub use point::Point;
 pub use point_cloud::PointCloud;
 pub use qdollar::QDollarRecognizer;
-pub use qdollar::QDollarResult;
+pub use clock::Clock;
+pub use compiled::{CompiledRecognizer, CompiledRecognizerBuilder};
+pub use distance::CloudDistance;
+pub use qdollar::{QDollarResult, RecognizeOptions, RecognizeStats, TemplateFilter, TemplateId};
+pub use qdollar::{LearnOutcome, TemplateStats};
 pub use error::QDollarError;
+pub use explain::{Explanation, MatchExplanation};
+pub use metadata::{TemplateMetadata, Vocabulary};
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use super::*;
 
@@ -22,6 +63,8 @@ mod tests {
     use plotlib::style::{PointMarker, PointStyle};
     #[cfg(test)]
     use plotlib::view::ContinuousView;
+    #[cfg(test)]
+    use proptest::prelude::*;
 
     #[test]
     fn test_add_and_recognize_gesture() {
@@ -34,7 +77,7 @@ mod tests {
             Point::new(1.0, 0.0, 1),
             Point::new(0.0, 0.0, 1),
         ];
-        recognizer.add_gesture("square".to_string(), square_points.clone());
+        recognizer.add_gesture("square".to_string(), square_points.clone()).unwrap();
 
         let result = recognizer.recognize(&square_points).unwrap();
         assert_eq!(result.name, "square");
@@ -67,7 +110,7 @@ mod tests {
             Point::new(1.0, 0.0, 1),
             Point::new(0.0, 0.0, 1),
         ];
-        recognizer.add_gesture("square".to_string(), square_points.clone());
+        recognizer.add_gesture("square".to_string(), square_points.clone()).unwrap();
 
         let triangle_points = vec![
             Point::new(0.0, 0.0, 1),
@@ -75,7 +118,7 @@ mod tests {
             Point::new(1.0, 0.0, 1),
             Point::new(0.0, 0.0, 1),
         ];
-        recognizer.add_gesture("triangle".to_string(), triangle_points.clone());
+        recognizer.add_gesture("triangle".to_string(), triangle_points.clone()).unwrap();
 
         let result_square = recognizer.recognize(&square_points).unwrap();
         assert_eq!(result_square.name, "square");
@@ -172,6 +215,1131 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_utils_resample_edge_cases() {
+        // Single point
+        let single = vec![Point::new(0.5, 0.5, 1)];
+        let resampled = utils::resample(&single, utils::NUM_POINTS);
+        assert_eq!(resampled.len(), utils::NUM_POINTS);
+        assert!(resampled.iter().all(|pt| *pt == single[0]));
+
+        // Duplicate consecutive points
+        let duplicates = vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(0.0, 0.0, 1),
+            Point::new(1.0, 0.0, 1),
+            Point::new(1.0, 0.0, 1),
+            Point::new(1.0, 1.0, 1),
+        ];
+        let resampled = utils::resample(&duplicates, utils::NUM_POINTS);
+        assert_eq!(resampled.len(), utils::NUM_POINTS);
+        assert!(resampled
+            .iter()
+            .all(|pt| pt.x.is_finite() && pt.y.is_finite()));
+        assert_eq!(resampled[utils::NUM_POINTS - 1], duplicates[4]);
+
+        // All zero-length strokes
+        let taps = vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(0.0, 0.0, 1),
+            Point::new(1.0, 1.0, 2),
+        ];
+        let resampled = utils::resample(&taps, utils::NUM_POINTS);
+        assert_eq!(resampled.len(), utils::NUM_POINTS);
+
+        // Empty input and degenerate counts
+        assert!(utils::resample(&[], utils::NUM_POINTS).is_empty());
+        assert_eq!(utils::resample(&duplicates, 1), vec![duplicates[0]]);
+        assert_eq!(utils::resample(&duplicates, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_utils_resample_multistroke_ids() {
+        // Two strokes: the interpolated points must never take the id of the
+        // next stroke while still on the previous one.
+        let points = vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(0.0, 1.0, 1),
+            Point::new(1.0, 0.0, 2),
+            Point::new(1.0, 1.0, 2),
+        ];
+        let resampled = utils::resample(&points, utils::NUM_POINTS);
+        assert_eq!(resampled.len(), utils::NUM_POINTS);
+        for pt in &resampled {
+            let expected_id = if pt.x < 0.5 { 1 } else { 2 };
+            assert_eq!(pt.id, expected_id, "{:?} has the wrong stroke id", pt);
+        }
+    }
+
+    fn multistroke_gesture() -> impl Strategy<Value = Vec<Point>> {
+        prop::collection::vec(
+            prop::collection::vec((-1000.0..1000.0f64, -1000.0..1000.0f64), 1..20),
+            1..6,
+        )
+        .prop_map(|strokes| {
+            strokes
+                .into_iter()
+                .enumerate()
+                .flat_map(|(id, stroke)| {
+                    stroke
+                        .into_iter()
+                        .map(move |(x, y)| Point::new(x, y, id as u32 + 1))
+                })
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_resample_returns_exactly_n_points(points in multistroke_gesture(), n in 1usize..100) {
+            let resampled = utils::resample(&points, n);
+            prop_assert_eq!(resampled.len(), n);
+            prop_assert_eq!(resampled[0], points[0]);
+            for pt in &resampled {
+                prop_assert!(pt.x.is_finite() && pt.y.is_finite());
+                prop_assert!(points.iter().any(|p| p.id == pt.id));
+            }
+        }
+
+        #[test]
+        fn prop_point_clouds_have_matching_sizes(a in multistroke_gesture(), b in multistroke_gesture()) {
+            let template = PointCloud::new("a".to_string(), a);
+            let candidate = PointCloud::new("b".to_string(), b);
+            prop_assert_eq!(template.points.len(), utils::NUM_POINTS);
+            prop_assert_eq!(candidate.points.len(), utils::NUM_POINTS);
+            prop_assert!(utils::cloud_match(&candidate, &template, f64::INFINITY).is_finite());
+        }
+
+        #[test]
+        fn prop_nearest_matches_scalar_reference(
+            // A coarse grid, so ties between points are common.
+            points in prop::collection::vec((-4i32..4, -4i32..4, any::<bool>()), 1..40),
+            x in -4i32..4,
+            y in -4i32..4,
+        ) {
+            use crate::scalar::{nearest_scalar, Coords, Scalar};
+
+            let cloud: Vec<Point> = points
+                .iter()
+                .map(|&(px, py, _)| Point::new(px as f64 / 3.0, py as f64 / 3.0, 1))
+                .collect();
+            let pt = [x as f64 / 3.0, y as f64 / 3.0];
+            let coords = Coords::<f64>::new(&cloud);
+            let penalty: Vec<f64> =
+                points.iter().map(|&(_, _, matched)| if matched { f64::MATCHED } else { 0.0 }).collect();
+            let (index, dist) = f64::nearest(pt, &coords, &penalty);
+            let (expected_index, expected_dist) = nearest_scalar(pt, &coords, &penalty);
+            prop_assert_eq!(index, expected_index);
+            prop_assert_eq!(dist.to_bits(), expected_dist.to_bits());
+
+            let coords = Coords::<f32>::new(&cloud);
+            let pt = [pt[0] as f32, pt[1] as f32];
+            let penalty: Vec<f32> = penalty.iter().map(|&p| p as f32).collect();
+            let (index, dist) = f32::nearest(pt, &coords, &penalty);
+            let (expected_index, expected_dist) = nearest_scalar(pt, &coords, &penalty);
+            prop_assert_eq!(index, expected_index);
+            prop_assert_eq!(dist.to_bits(), expected_dist.to_bits());
+        }
+
+        #[test]
+        fn prop_f32_distances_track_f64(a in multistroke_gesture(), b in multistroke_gesture()) {
+            let template = PointCloud::new("a".to_string(), a);
+            let candidate = PointCloud::new("b".to_string(), b);
+            let mut counters = utils::MatchCounters::default();
+            let exact = utils::cloud_match_as::<f64>(&candidate, &template, f64::INFINITY, &mut counters);
+            let single = utils::cloud_match_as::<f32>(&candidate, &template, f64::INFINITY, &mut counters);
+            prop_assert!((single - exact).abs() <= 1e-5 * exact + 1e-9);
+        }
+
+        #[test]
+        fn prop_assignment_matches_brute_force(
+            rows in 0usize..5,
+            extra in 0usize..3,
+            costs in prop::collection::vec(0.0f64..10.0, 64),
+        ) {
+            let cols = rows + extra;
+            let cost = |i: usize, j: usize| costs[i * 8 + j];
+            let (assignment, total) = assignment::min_cost_assignment(rows, cols, cost);
+
+            let mut columns: Vec<usize> = assignment.clone();
+            columns.sort_unstable();
+            columns.dedup();
+            prop_assert_eq!(columns.len(), rows);
+            prop_assert!(assignment.iter().all(|&j| j < cols));
+
+            // Every injection of rows into columns.
+            fn best(
+                row: usize,
+                rows: usize,
+                cols: usize,
+                used: &mut Vec<bool>,
+                cost: &dyn Fn(usize, usize) -> f64,
+            ) -> f64 {
+                if row == rows {
+                    return 0.0;
+                }
+                let mut min = f64::INFINITY;
+                for j in 0..cols {
+                    if !used[j] {
+                        used[j] = true;
+                        min = min.min(cost(row, j) + best(row + 1, rows, cols, used, cost));
+                        used[j] = false;
+                    }
+                }
+                min
+            }
+            let expected = best(0, rows, cols, &mut vec![false; cols], &cost);
+            prop_assert!((total - expected).abs() <= 1e-9);
+        }
+
+        #[test]
+        fn prop_optimal_match_bounds_greedy(a in multistroke_gesture(), b in multistroke_gesture()) {
+            let template = PointCloud::new("a".to_string(), a);
+            let candidate = PointCloud::new("b".to_string(), b);
+            let mut counters = utils::MatchCounters::default();
+            let optimal = utils::optimal_match(&candidate, &template, f64::INFINITY, &mut counters);
+
+            // Unweighted, no greedy pairing costs less than the optimal one.
+            let n = candidate.points.len();
+            for start in 0..n {
+                let mut sum = 0.0;
+                let (pts1, pts2) = (&candidate.points, &template.points);
+                utils::greedy_match(pts1, pts2, start, f64::INFINITY, |_, _, _, d| sum += d);
+                prop_assert!(optimal <= sum * (n + 1) as f64 / 2.0 + 1e-9);
+            }
+        }
+
+        #[test]
+        fn prop_cloud_distances(a in multistroke_gesture(), b in multistroke_gesture()) {
+            use crate::distance::{ApproximateEmd, Chamfer, ModifiedHausdorff};
+
+            let a = PointCloud::new("a".to_string(), a);
+            let b = PointCloud::new("b".to_string(), b);
+            let mut counters = utils::MatchCounters::default();
+            let mut d = |distance: &dyn CloudDistance, x: &PointCloud, y: &PointCloud| {
+                distance.distance(x, y, f64::INFINITY, &mut counters)
+            };
+
+            let exact_chamfer = Chamfer { use_lut: false };
+            for distance in [
+                &ModifiedHausdorff as &dyn CloudDistance,
+                &exact_chamfer,
+                &ApproximateEmd::default(),
+            ] {
+                prop_assert_eq!(d(distance, &a, &a), 0.0);
+                prop_assert!((d(distance, &a, &b) - d(distance, &b, &a)).abs() <= 1e-9);
+            }
+            prop_assert!(d(&Chamfer::default(), &a, &b) >= d(&exact_chamfer, &a, &b) - 1e-12);
+
+            let n = a.points.len();
+            let (_, emd) = assignment::min_cost_assignment(n, n, |i, j| {
+                utils::euclidean_distance(&a.points[i], &b.points[j])
+            });
+            prop_assert!(d(&ApproximateEmd::default(), &a, &b) <= emd / n as f64 + 1e-9);
+        }
+
+        #[test]
+        fn prop_vp_tree_matches_brute_force(
+            features in prop::collection::vec(prop::array::uniform6(-1.0f64..1.0), 0..60),
+            query in prop::array::uniform6(-1.0f64..1.0),
+            k in 0usize..10,
+            modulus in 1usize..4,
+        ) {
+            use crate::index::{Features, VpTree};
+
+            let features: Vec<Features> = features.into_iter().map(Features).collect();
+            let query = Features(query);
+            let tree = VpTree::new(features.clone());
+            let mut expected: Vec<(usize, f64)> = features
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i % modulus == 0)
+                .map(|(i, f)| (i, query.distance(f)))
+                .collect();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            expected.truncate(k);
+            prop_assert_eq!(tree.nearest(&query, k, |i| i % modulus == 0), expected);
+        }
+
+        #[test]
+        fn prop_corrupt_binary_files_are_rejected(
+            at in any::<prop::sample::Index>(),
+            flip in 1u8..=255,
+            cut in any::<prop::sample::Index>(),
+            garbage in prop::collection::vec(any::<u8>(), 0..96),
+        ) {
+            use crate::binary::Verify;
+
+            let bytes = binary_fixture();
+            let load = |bytes: Vec<u8>| {
+                CompiledRecognizer::from_bytes(bytes, QDollarConfig::default(), Verify::All)
+            };
+            // The checksums catch any single changed byte.
+            let mut flipped = bytes.to_vec();
+            flipped[at.index(bytes.len())] ^= flip;
+            prop_assert!(load(flipped).is_err());
+            prop_assert!(load(bytes[..cut.index(bytes.len())].to_vec()).is_err());
+            prop_assert!(load(garbage).is_err());
+        }
+    }
+
+    // Matching is slow in debug builds, so these run fewer cases.
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn prop_empty_gestures_are_never_matched(points in multistroke_gesture()) {
+            let mut recognizer = QDollarRecognizer::new();
+            recognizer.add_gesture("gesture".to_string(), points.clone()).unwrap();
+            prop_assert_eq!(recognizer.recognize(&[]).err(), Some(QDollarError::EmptyGesture));
+
+            prop_assert_eq!(
+                recognizer.add_gesture("empty".to_string(), vec![]),
+                Err(QDollarError::EmptyGesture)
+            );
+            prop_assert_eq!(recognizer.recognize(&points).unwrap().name, "gesture");
+        }
+
+        #[test]
+        fn prop_corrupt_binary_files_never_panic(
+            at in any::<prop::sample::Index>(),
+            flip in 1u8..=255,
+        ) {
+            use crate::binary::Verify;
+
+            let bytes = binary_fixture();
+            let mut flipped = bytes.to_vec();
+            flipped[at.index(bytes.len())] ^= flip;
+            // Unchecked points and LUTs can be wrong, but must not crash
+            // matching.
+            let config = QDollarConfig::default();
+            if let Ok(compiled) =
+                CompiledRecognizer::from_bytes(flipped.clone(), config.clone(), Verify::Header)
+            {
+                let _ = compiled.recognize(&square(0.1));
+            }
+            // Past the checksums, the parser must still fail cleanly.
+            fix_checksums(&mut flipped);
+            if let Ok(compiled) = CompiledRecognizer::from_bytes(flipped, config, Verify::All) {
+                let _ = compiled.recognize_n_best(&square(0.1), 3);
+            }
+        }
+    }
+
+    fn binary_builder() -> CompiledRecognizerBuilder {
+        let mut builder = CompiledRecognizer::builder(QDollarConfig::default());
+        let metadata = TemplateMetadata {
+            author: Some("ann".to_string()),
+            tags: vec!["shape".to_string()],
+            ..TemplateMetadata::default()
+        };
+        builder
+            .add_gesture("square".to_string(), square(0.0))
+            .add_gesture_with_metadata("triangle".to_string(), triangle(0.0), metadata);
+        builder
+    }
+
+    fn binary_fixture() -> &'static [u8] {
+        static BYTES: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+        BYTES.get_or_init(|| binary_builder().build().unwrap().to_bytes().unwrap())
+    }
+
+    // Recomputes both checksums, if the header still gives a usable data offset.
+    fn fix_checksums(bytes: &mut [u8]) {
+        let offset = u64::from_le_bytes(bytes[32..40].try_into().unwrap());
+        let Ok(offset) = usize::try_from(offset) else {
+            return;
+        };
+        if offset < 64 || offset > bytes.len() {
+            return;
+        }
+        let header = binary::checksum(&[&bytes[..48], &bytes[64..offset]]);
+        let data = binary::checksum(&[&bytes[offset..]]);
+        bytes[48..56].copy_from_slice(&header.to_le_bytes());
+        bytes[56..64].copy_from_slice(&data.to_le_bytes());
+    }
+
+    #[test]
+    fn test_binary_templates() {
+        use crate::binary::Verify;
+
+        let config = QDollarConfig::default();
+        let bytes = binary_fixture();
+        let built = binary_builder().build().unwrap();
+        for verify in [Verify::All, Verify::Header] {
+            let loaded = CompiledRecognizer::from_bytes(bytes.to_vec(), config.clone(), verify);
+            let loaded = loaded.unwrap();
+            assert_eq!(loaded.to_bytes().unwrap(), bytes);
+            for sample in [square(0.1), triangle(0.1), triangle(-0.2)] {
+                let expected = built.recognize(&sample).unwrap();
+                let actual = loaded.recognize(&sample).unwrap();
+                assert_eq!(actual.name, expected.name);
+                assert_eq!(actual.score, expected.score);
+                assert_eq!(actual.metadata, expected.metadata);
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("qdollar-{}.qdb", std::process::id()));
+        built.save(&path).unwrap();
+        let from_file = CompiledRecognizer::load(&path, config.clone(), Verify::All).unwrap();
+        assert_eq!(from_file.recognize(&triangle(0.1)).unwrap().name, "triangle");
+        #[cfg(all(feature = "mmap", unix))]
+        {
+            let mapped = CompiledRecognizer::map(&path, config.clone(), Verify::Header).unwrap();
+            assert_eq!(mapped.to_bytes().unwrap(), bytes);
+            assert_eq!(mapped.recognize(&square(0.1)).unwrap().name, "square");
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            CompiledRecognizer::load(&path, config.clone(), Verify::All),
+            Err(QDollarError::Io(_))
+        ));
+
+        let error = |bytes: Vec<u8>| {
+            match CompiledRecognizer::from_bytes(bytes, QDollarConfig::default(), Verify::All) {
+                Err(QDollarError::Serialization(message)) => message,
+                other => panic!("expected a serialization error, got {:?}", other),
+            }
+        };
+        let mut newer = bytes.to_vec();
+        newer[8] = 3;
+        assert!(error(newer).contains("version 3"));
+        let mut swapped = bytes.to_vec();
+        swapped.swap(10, 11);
+        assert!(error(swapped).contains("other byte order"));
+        let optimal = QDollarConfig {
+            matching: Matching::Optimal,
+            ..QDollarConfig::default()
+        };
+        assert!(matches!(
+            CompiledRecognizer::from_bytes(bytes.to_vec(), optimal, Verify::All),
+            Err(QDollarError::Unsupported(_))
+        ));
+
+        // Templates only load with the config that built them.
+        let per_stroke = QDollarConfig {
+            resample_strategy: ResampleStrategy::PerStroke,
+            ..QDollarConfig::default()
+        };
+        let mut builder = CompiledRecognizer::builder(per_stroke.clone());
+        builder.add_gesture("square".to_string(), square(0.0));
+        let per_stroke_bytes = builder.build().unwrap().to_bytes().unwrap();
+        let loaded =
+            CompiledRecognizer::from_bytes(per_stroke_bytes.clone(), per_stroke, Verify::All);
+        assert_eq!(loaded.unwrap().recognize(&square(0.1)).unwrap().name, "square");
+        for other in [
+            QDollarConfig::default(),
+            QDollarConfig {
+                precision: Precision::F32,
+                ..QDollarConfig::default()
+            },
+            QDollarConfig {
+                preprocessing: vec![Preprocess::Dedupe { min_distance: 0.0 }],
+                ..QDollarConfig::default()
+            },
+        ] {
+            let bytes = per_stroke_bytes.clone();
+            let loaded = CompiledRecognizer::from_bytes(bytes, other, Verify::All);
+            assert_eq!(loaded.err(), Some(QDollarError::ConfigMismatch));
+        }
+        let mut mismatched = bytes.to_vec();
+        mismatched[20] ^= 1;
+        fix_checksums(&mut mismatched);
+        assert_eq!(
+            CompiledRecognizer::from_bytes(mismatched, config, Verify::All).err(),
+            Some(QDollarError::ConfigMismatch)
+        );
+
+        let mut builder = binary_builder();
+        builder.add_gesture("nothing".to_string(), Vec::new());
+        assert!(matches!(builder.build(), Err(QDollarError::InvalidTemplate(_))));
+    }
+
+    #[test]
+    fn test_resample_strategies_return_n_points() {
+        let points = vec![
+            Point::with_time(0.0, 0.0, 1, 0.0),
+            Point::with_time(0.0, 1.0, 1, 10.0),
+            Point::with_time(1.0, 1.0, 1, 40.0),
+            Point::with_time(1.0, 0.0, 1, 50.0),
+            Point::with_time(0.5, 1.5, 2, 80.0),
+        ];
+        let strategies = [
+            ResampleStrategy::Equidistant,
+            ResampleStrategy::EquidistantTime,
+            ResampleStrategy::curvature_adaptive(),
+            ResampleStrategy::PerStroke,
+        ];
+        for strategy in strategies {
+            let resampled = strategy.resample(&points, utils::NUM_POINTS);
+            assert_eq!(resampled.len(), utils::NUM_POINTS, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn test_resample_by_time() {
+        // The first segment takes as long as the other two together, so it
+        // should get half of the points.
+        let points = vec![
+            Point::with_time(0.0, 0.0, 1, 0.0),
+            Point::with_time(0.0, 1.0, 1, 100.0),
+            Point::with_time(1.0, 1.0, 1, 150.0),
+            Point::with_time(1.0, 0.0, 1, 200.0),
+        ];
+        let resampled = utils::resample_by_time(&points, 9);
+        assert_eq!(resampled.len(), 9);
+        assert!(resampled[..5].iter().all(|pt| pt.x == 0.0));
+        assert!((resampled[4].y - 1.0).abs() < 1e-9);
+
+        // Without timestamps it behaves like the path resampling.
+        let untimed: Vec<Point> = points
+            .iter()
+            .map(|pt| Point::new(pt.x, pt.y, pt.id))
+            .collect();
+        assert_eq!(
+            utils::resample_by_time(&untimed, 9),
+            utils::resample(&untimed, 9)
+        );
+    }
+
+    #[test]
+    fn test_resample_curvature_adaptive_keeps_corners() {
+        let points = vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(0.0, 1.0, 1),
+            Point::new(1.0, 1.0, 1),
+            Point::new(1.0, 0.0, 1),
+            Point::new(0.3, 0.0, 1),
+        ];
+        let resampled =
+            utils::resample_curvature_adaptive(&points, 10, std::f64::consts::FRAC_PI_4);
+        assert_eq!(resampled.len(), 10);
+        for corner in &points {
+            assert!(resampled.contains(corner), "{:?} was not kept", corner);
+        }
+    }
+
+    #[test]
+    fn test_resample_per_stroke_keeps_dots() {
+        // A dot between two strokes falls between two equidistant samples.
+        let points = vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(0.0, 1.0, 1),
+            Point::new(0.5, 1.5, 2),
+            Point::new(1.0, 0.0, 3),
+            Point::new(1.0, 1.0, 3),
+        ];
+        let resampled = utils::resample_per_stroke(&points, utils::NUM_POINTS);
+        assert_eq!(resampled.len(), utils::NUM_POINTS);
+        assert!(resampled.contains(&points[2]));
+        assert!(!utils::resample(&points, utils::NUM_POINTS).contains(&points[2]));
+    }
+
+    #[test]
+    fn test_eval_compare_strategies() {
+        let gesture = |dx: f64, dot: Option<f64>| {
+            let mut points = vec![Point::new(dx, 0.0, 1), Point::new(dx, 1.0, 1)];
+            if let Some(dot) = dot {
+                points.push(Point::new(dx + 0.5, 1.0 + dot, 2));
+            }
+            points.push(Point::new(dx + 1.0, 0.0, 3));
+            points.push(Point::new(dx + 1.0, 1.0, 3));
+            points
+        };
+        let templates = vec![
+            eval::Sample::new("dotted".to_string(), gesture(0.0, Some(0.5))),
+            eval::Sample::new("plain".to_string(), gesture(0.0, None)),
+        ];
+        let samples = vec![
+            eval::Sample::new("dotted".to_string(), gesture(0.1, Some(0.4))),
+            eval::Sample::new("dotted".to_string(), gesture(0.0, Some(0.6))),
+            eval::Sample::new("plain".to_string(), gesture(0.1, None)),
+            eval::Sample::new("plain".to_string(), gesture(0.2, None)),
+        ];
+
+        let reports = eval::compare_strategies(
+            &QDollarConfig::default(),
+            &[ResampleStrategy::Equidistant, ResampleStrategy::PerStroke],
+            &templates,
+            &samples,
+        );
+        assert_eq!(reports.len(), 2);
+        let (equidistant, per_stroke) = (&reports[0].1, &reports[1].1);
+        assert_eq!(per_stroke.total, samples.len());
+        assert_eq!(per_stroke.accuracy, 1.0);
+        assert!(per_stroke.accuracy > equidistant.accuracy);
+    }
+
+    #[test]
+    fn test_eval_reports_skipped_templates() {
+        let templates = vec![
+            eval::Sample::new("square".to_string(), square(0.0)),
+            eval::Sample::new("empty".to_string(), Vec::new()),
+            eval::Sample::new("triangle".to_string(), triangle(0.0)),
+        ];
+        let samples = vec![eval::Sample::new("square".to_string(), square(0.1))];
+        let config = QDollarConfig::default();
+
+        let report = eval::evaluate_config(&config, &templates, &samples);
+        assert_eq!(report.skipped_templates, [1]);
+        assert_eq!(report.accuracy, 1.0);
+        let synthesizer = synth::Synthesizer::default();
+        let report = eval::evaluate_synthesized(&config, &templates, &samples, &synthesizer, 2);
+        assert_eq!(report.skipped_templates, [1]);
+        assert_eq!(report.accuracy, 1.0);
+    }
+
+    #[test]
+    fn test_eval_compare_matching() {
+        let skews = [-0.3, 0.0, 0.3];
+        let mut templates = Vec::new();
+        let mut samples = Vec::new();
+        for (name, shape) in [("square", square as fn(f64) -> Vec<Point>), ("triangle", triangle)] {
+            for &skew in &skews {
+                templates.push(eval::Sample::new(name.to_string(), shape(skew)));
+                samples.push(eval::Sample::new(name.to_string(), shape(skew + 0.1)));
+            }
+        }
+
+        let reports = eval::compare_matching(
+            &QDollarConfig::default(),
+            &[Matching::Greedy, Matching::Optimal],
+            &templates,
+            &samples,
+        );
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].1.accuracy, 1.0);
+        assert_eq!(reports[1].0, Matching::Optimal);
+        assert_eq!(reports[1].1.accuracy, 1.0);
+
+        // A sample identical to a template is at distance zero either way.
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            matching: Matching::Optimal,
+            ..QDollarConfig::default()
+        });
+        recognizer.add_gesture("square".to_string(), square(0.0)).unwrap();
+        recognizer.add_gesture("triangle".to_string(), triangle(0.0)).unwrap();
+        let result = recognizer.recognize(&triangle(0.0)).unwrap();
+        assert_eq!(result.name, "triangle");
+        assert_eq!(result.score, 1.0);
+    }
+
+    #[test]
+    fn test_recognizer_with_cloud_distances() {
+        use crate::distance::{ApproximateEmd, Chamfer, ModifiedHausdorff};
+
+        let distances: [std::sync::Arc<dyn CloudDistance>; 4] = [
+            std::sync::Arc::new(ModifiedHausdorff),
+            std::sync::Arc::new(Chamfer::default()),
+            std::sync::Arc::new(Chamfer { use_lut: false }),
+            std::sync::Arc::new(ApproximateEmd::default()),
+        ];
+        for distance in distances {
+            let mut recognizer = QDollarRecognizer::new();
+            recognizer.set_distance(distance);
+            recognizer.add_gesture("square".to_string(), square(0.0)).unwrap();
+            recognizer.add_gesture("triangle".to_string(), triangle(0.0)).unwrap();
+            for skew in [-0.1, 0.1] {
+                assert_eq!(recognizer.recognize(&square(skew)).unwrap().name, "square");
+                assert_eq!(recognizer.recognize(&triangle(skew)).unwrap().name, "triangle");
+            }
+            assert_eq!(recognizer.recognize(&square(0.0)).unwrap().score, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_stroke_structure() {
+        // Two horizontal lines, bottom first unless `swap`.
+        let lines = |swap: bool| {
+            let (first, second) = if swap { (1.0, 0.0) } else { (0.0, 1.0) };
+            vec![
+                Point::new(0.0, first, 1),
+                Point::new(1.0, first, 1),
+                Point::new(0.0, second, 2),
+                Point::new(1.0, second, 2),
+            ]
+        };
+        let recognize = |config: QDollarConfig,
+                         templates: &[(&str, Vec<Point>)],
+                         sample: &[Point]| {
+            let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+                resample_strategy: ResampleStrategy::PerStroke,
+                ..config
+            });
+            for (name, points) in templates {
+                recognizer.add_gesture(name.to_string(), points.clone()).unwrap();
+            }
+            recognizer.recognize(sample).unwrap().name
+        };
+
+        // A Z drawn in one stroke, or in two with a break after the diagonal.
+        let zed = |skew: f64, ids: [u32; 2]| {
+            vec![
+                Point::new(0.0, 1.0, ids[0]),
+                Point::new(1.0 + skew, 1.0, ids[0]),
+                Point::new(0.0, 0.0, ids[0]),
+                Point::new(0.0, 0.0, ids[1]),
+                Point::new(1.0 - skew, 0.0, ids[1]),
+            ]
+        };
+        let templates = [("one", zed(0.0, [1, 1])), ("two", zed(0.1, [1, 2]))];
+        let sample = zed(0.0, [1, 2]);
+        assert_eq!(recognize(QDollarConfig::default(), &templates, &sample), "one");
+        for stroke_count in [
+            StrokeCount::Penalty { per_stroke: 5.0 },
+            StrokeCount::Filter { max_difference: 0 },
+        ] {
+            let config = QDollarConfig {
+                stroke_count,
+                ..QDollarConfig::default()
+            };
+            assert_eq!(recognize(config, &templates, &sample), "two");
+        }
+
+        let mut reversed = square(0.0);
+        reversed.reverse();
+        let templates = [("reversed", reversed), ("forward", square(0.1))];
+        let by_direction = QDollarConfig {
+            stroke_features: StrokeFeatures {
+                direction: 0.5,
+                order: 0.0,
+            },
+            ..QDollarConfig::default()
+        };
+        assert_eq!(recognize(QDollarConfig::default(), &templates, &square(0.0)), "reversed");
+        assert_eq!(recognize(by_direction, &templates, &square(0.0)), "forward");
+
+        let templates = [("top first", lines(true)), ("bottom first", lines(false))];
+        let by_order = QDollarConfig {
+            stroke_features: StrokeFeatures {
+                direction: 0.0,
+                order: 0.5,
+            },
+            ..QDollarConfig::default()
+        };
+        let sample = lines(false);
+        assert_eq!(recognize(QDollarConfig::default(), &templates, &sample), "top first");
+        assert_eq!(recognize(by_order, &templates, &sample), "bottom first");
+    }
+
+    #[test]
+    fn test_stroke_count_keeps_short_strokes() {
+        // Two bars with a dot between them, the dot too short to survive the
+        // default resampling.
+        let bars = |dot: bool| {
+            let mut points = vec![Point::new(0.0, 0.0, 1), Point::new(1.0, 0.0, 1)];
+            if dot {
+                points.push(Point::new(0.5, 0.5, 2));
+                points.push(Point::new(0.51, 0.5, 2));
+            }
+            points.push(Point::new(0.0, 1.0, 3));
+            points.push(Point::new(1.0, 1.0, 3));
+            points
+        };
+        let cloud = PointCloud::new("dotted".to_string(), bars(true));
+        assert_eq!(utils::stroke_count(&cloud.points), 2);
+        assert_eq!(cloud.stroke_count(), 3);
+
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            stroke_count: StrokeCount::Filter { max_difference: 0 },
+            ..QDollarConfig::default()
+        });
+        recognizer.add_gesture("plain".to_string(), bars(false)).unwrap();
+        recognizer.add_gesture("dotted".to_string(), bars(true)).unwrap();
+        assert_eq!(recognizer.recognize(&bars(true)).unwrap().name, "dotted");
+        assert_eq!(recognizer.recognize(&bars(false)).unwrap().name, "plain");
+
+        let mut loaded = QDollarRecognizer::new();
+        loaded.load_json(&recognizer.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.user_gestures()[1].stroke_count(), 3);
+        // Files written before stroke counts were stored count the strokes
+        // left after resampling.
+        let json = recognizer.to_json().unwrap().replace(",\"stroke_count\":3", "");
+        loaded.load_json(&json).unwrap();
+        assert_eq!(loaded.user_gestures()[1].stroke_count(), 2);
+    }
+
+    #[test]
+    fn test_template_metadata_and_vocabulary() {
+        assert!(metadata::in_class("letters/a", "letters"));
+        assert!(metadata::in_class("letters/a", "letters/a"));
+        assert!(!metadata::in_class("letters/a", "letter"));
+        assert!(!metadata::in_class("letters", "letters/a"));
+        assert_eq!(metadata::parent_class("letters/a"), Some("letters"));
+        assert_eq!(metadata::parent_class("letters"), None);
+
+        let tagged = |tag: &str, payload: &str| TemplateMetadata {
+            author: Some("user-7".to_string()),
+            tags: vec![tag.to_string()],
+            payload: Some(payload.to_string()),
+            ..TemplateMetadata::default()
+        };
+        let mut recognizer = QDollarRecognizer::new();
+        recognizer.add_gesture_with_metadata(
+            "shapes/square".to_string(),
+            square(0.0),
+            tagged("editor", "cmd:box"),
+        ).unwrap();
+        recognizer.add_gesture_with_metadata(
+            "shapes/triangle".to_string(),
+            triangle(0.0),
+            tagged("viewer", "cmd:play"),
+        ).unwrap();
+        recognizer.add_gesture("letters/v".to_string(), triangle(0.05)).unwrap();
+
+        let result = recognizer.recognize(&square(0.1)).unwrap();
+        assert_eq!(result.name, "shapes/square");
+        assert_eq!(result.template, TemplateId::User(0));
+        assert_eq!(result.metadata.payload.as_deref(), Some("cmd:box"));
+
+        let triangle_sample = triangle(0.05);
+        let letters = Vocabulary::classes(["letters"]);
+        let shapes = Vocabulary::classes(["shapes"]);
+        assert_eq!(recognizer.recognize(&triangle_sample).unwrap().name, "letters/v");
+        assert_eq!(recognizer.recognize_in(&triangle_sample, &letters).unwrap().name, "letters/v");
+        assert_eq!(
+            recognizer.recognize_in(&triangle_sample, &shapes).unwrap().name,
+            "shapes/triangle"
+        );
+        let result = recognizer
+            .recognize_in(&square(0.1), &Vocabulary::tags(["viewer"]))
+            .unwrap();
+        assert_eq!(result.name, "shapes/triangle");
+        assert_eq!(result.template, TemplateId::User(1));
+        assert_eq!(
+            recognizer
+                .recognize_in(&square(0.1), &Vocabulary::classes(["sounds"]))
+                .unwrap_err(),
+            QDollarError::NoRegisteredGestures
+        );
+
+        let mut shortlisted = QDollarRecognizer::with_config(QDollarConfig {
+            prefilter: Prefilter::Nearest { k: 1 },
+            ..QDollarConfig::default()
+        });
+        shortlisted.set_base_gestures(std::sync::Arc::new(recognizer.user_gestures().to_vec()));
+        let result = shortlisted.recognize_in(&triangle_sample, &shapes).unwrap();
+        assert_eq!(result.name, "shapes/triangle");
+        assert_eq!(result.template, TemplateId::Base(1));
+
+        let json = recognizer.to_json().unwrap();
+        let mut loaded = QDollarRecognizer::new();
+        loaded.load_json(&json).unwrap();
+        for (a, b) in loaded.user_gestures().iter().zip(recognizer.user_gestures()) {
+            assert_eq!(a.metadata, b.metadata);
+        }
+        assert!(loaded.user_gestures()[2].metadata.is_empty());
+    }
+
+    #[test]
+    fn test_recognize_with_options() {
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            collect_stats: true,
+            ..QDollarConfig::default()
+        });
+        recognizer.add_gesture("shapes/square".to_string(), square(0.0)).unwrap();
+        recognizer.add_gesture("shapes/triangle".to_string(), triangle(0.0)).unwrap();
+        recognizer.add_gesture_with_metadata(
+            "letters/v".to_string(),
+            triangle(0.05),
+            TemplateMetadata {
+                tags: vec!["text".to_string()],
+                ..TemplateMetadata::default()
+            },
+        ).unwrap();
+        let sample = triangle(0.04);
+
+        let is_text = |template: &PointCloud| template.metadata.has_tag("text");
+        let denied = RecognizeOptions {
+            filter: TemplateFilter::Deny(&is_text),
+            n: 3,
+            ..RecognizeOptions::default()
+        };
+        let results = recognizer.recognize_with(&sample, &denied).unwrap();
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["shapes/triangle", "shapes/square"]);
+        // Left-out templates are never matched.
+        assert_eq!(results[0].stats.unwrap().templates, 2);
+
+        let allowed = RecognizeOptions {
+            filter: TemplateFilter::Allow(&is_text),
+            ..RecognizeOptions::default()
+        };
+        let results = recognizer.recognize_with(&sample, &allowed).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "letters/v");
+        assert_eq!(results[0].stats.unwrap().templates, 1);
+
+        // A low prior on the letters hands the win to the triangle, and a
+        // zero prior on all shapes leaves them out.
+        let mut options = RecognizeOptions::default();
+        assert_eq!(recognizer.recognize_with(&sample, &options).unwrap()[0].name, "letters/v");
+        options.priors.insert("letters".to_string(), 1e-6);
+        assert_eq!(options.prior("letters/v"), 1e-6);
+        assert_eq!(options.prior("shapes/square"), 1.0);
+        let result = &recognizer.recognize_with(&sample, &options).unwrap()[0];
+        assert_eq!(result.name, "shapes/triangle");
+        options.priors.insert("shapes".to_string(), 0.0);
+        options.n = 3;
+        let results = recognizer.recognize_with(&sample, &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "letters/v");
+        assert_eq!(results[0].stats.unwrap().templates, 1);
+    }
+
+    #[test]
+    fn test_synth_variants() {
+        let two_strokes = vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(1.0, 0.0, 1),
+            Point::new(0.5, -0.5, 2),
+            Point::new(0.5, 0.5, 2),
+        ];
+        let synthesizer = synth::Synthesizer::default();
+        let variants = synthesizer.variants(&two_strokes, 8);
+        assert_eq!(variants, synthesizer.variants(&two_strokes, 8));
+        let reseeded = synth::Synthesizer {
+            seed: 1,
+            ..synthesizer.clone()
+        };
+        assert_ne!(variants, reseeded.variants(&two_strokes, 8));
+        for variant in &variants {
+            assert_eq!(variant.len(), two_strokes.len());
+            assert_eq!(utils::stroke_count(variant), 2);
+        }
+        // Eight variants are enough to see a reordered and a reversed stroke.
+        assert!(variants.iter().any(|v| v[0].id == 2));
+        assert!(variants.iter().any(|v| v[0].x > v[1].x || v[2].y > v[3].y));
+
+        let gpsr = synth::Synthesizer {
+            max_rotation: 0.0,
+            max_scale: 0.0,
+            jitter: 0.0,
+            gpsr: Some(synth::Gpsr::default()),
+            ..synthesizer
+        };
+        for variant in gpsr.variants(&square(0.0), 4) {
+            assert_eq!(variant.len(), utils::NUM_POINTS);
+            for pair in variant.windows(2) {
+                let step = utils::euclidean_distance(&pair[0], &pair[1]);
+                assert!(step < 1e-9 || (step - 1.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_synth_improves_accuracy() {
+        let polyline = |pts: &[(f64, f64)]| -> Vec<Point> {
+            pts.iter().map(|&(x, y)| Point::new(x, y, 1)).collect()
+        };
+        let shapes = [
+            ("square", square(0.0)),
+            ("triangle", triangle(0.0)),
+            ("check", polyline(&[(0.0, 0.5), (0.3, 0.0), (1.0, 1.0)])),
+            ("v", polyline(&[(0.0, 1.0), (0.5, 0.0), (1.0, 1.0)])),
+            ("zig", polyline(&[(0.0, 1.0), (1.0, 1.0), (0.0, 0.0), (1.0, 0.0)])),
+        ];
+        let templates: Vec<eval::Sample> = shapes
+            .iter()
+            .map(|(name, points)| eval::Sample::new(name.to_string(), points.clone()))
+            .collect();
+        // Rotated and stretched well past the one template per class.
+        let mut samples = Vec::new();
+        for (name, points) in &shapes {
+            let distortions = [(-0.35, 0.8), (-0.2, 1.2), (0.2, 0.85), (0.35, 1.15), (0.3, 0.7)];
+            for (angle, stretch) in distortions {
+                let (sin, cos) = f64::sin_cos(angle);
+                let distorted = points
+                    .iter()
+                    .map(|pt| {
+                        let x = (pt.x * cos - pt.y * sin) * stretch;
+                        Point::new(x, pt.x * sin + pt.y * cos, pt.id)
+                    })
+                    .collect();
+                samples.push(eval::Sample::new(name.to_string(), distorted));
+            }
+        }
+
+        let config = QDollarConfig::default();
+        let baseline = eval::evaluate_config(&config, &templates, &samples);
+        let synthesizer = synth::Synthesizer {
+            max_rotation: 0.4,
+            max_scale: 0.3,
+            ..synth::Synthesizer::default()
+        };
+        let augmented = eval::evaluate_synthesized(&config, &templates, &samples, &synthesizer, 8);
+        let gpsr = synth::Synthesizer {
+            gpsr: Some(synth::Gpsr::default()),
+            ..synthesizer
+        };
+        let resampled = eval::evaluate_synthesized(&config, &templates, &samples, &gpsr, 8);
+        assert!(augmented.accuracy > baseline.accuracy);
+        assert!(resampled.accuracy > baseline.accuracy);
+
+        let mut recognizer = QDollarRecognizer::new();
+        recognizer.add_synthetic_gestures("square".to_string(), &square(0.0), 3, &gpsr).unwrap();
+        assert!(recognizer
+            .user_gestures()
+            .iter()
+            .all(|template| template.metadata.has_tag(synth::SYNTHETIC_TAG)));
+    }
+
+    #[test]
+    fn test_preprocessing() {
+        let polyline = |pts: &[(f64, f64)], id: u32| -> Vec<Point> {
+            pts.iter().map(|&(x, y)| Point::new(x, y, id)).collect()
+        };
+
+        let repeated = [
+            polyline(&[(0.0, 0.0), (0.0, 0.0), (0.05, 0.0), (1.0, 0.0)], 1),
+            polyline(&[(1.0, 0.0), (2.0, 0.0)], 2),
+        ]
+        .concat();
+        let deduped = Preprocess::Dedupe { min_distance: 0.1 }.apply(&repeated);
+        assert_eq!(deduped, [&repeated[..1], &repeated[3..]].concat());
+
+        let zigzag: Vec<Point> = (0..20)
+            .map(|i| Point::new(i as f64, (i % 2) as f64, 1))
+            .collect();
+        let wiggle = |points: &[Point]| {
+            points[1..points.len() - 1]
+                .iter()
+                .map(|pt| (pt.y - 0.5).abs())
+                .fold(0.0, f64::max)
+        };
+        let averaged = Preprocess::MovingAverage { window: 3 }.apply(&zigzag);
+        assert_eq!(averaged.len(), zigzag.len());
+        assert!(wiggle(&averaged) < 0.2);
+        let timed: Vec<Point> = zigzag
+            .iter()
+            .map(|pt| Point::with_time(pt.x, pt.y, pt.id, pt.x / 60.0))
+            .collect();
+        let one_euro = Preprocess::OneEuro {
+            min_cutoff: 1.0,
+            beta: 0.0,
+            d_cutoff: 1.0,
+        };
+        let filtered = one_euro.apply(&timed);
+        assert_eq!(filtered.len(), timed.len());
+        assert_eq!(filtered[0], timed[0]);
+        assert!(wiggle(&filtered[10..]) < 0.2);
+
+        let hooked = polyline(&[(0.5, 0.5), (0.0, 0.0), (5.0, 0.0), (10.0, 0.0), (9.5, 0.5)], 1);
+        let unhooked = Preprocess::RemoveHooks {
+            max_fraction: 0.1,
+            min_angle: core::f64::consts::FRAC_PI_2,
+        }
+        .apply(&hooked);
+        assert_eq!(unhooked, hooked[1..4]);
+
+        let tap = polyline(&[(0.5, 0.5), (0.52, 0.5)], 2);
+        let with_tap = [square(0.0), tap.clone()].concat();
+        let min_stroke_length = Preprocess::MinStrokeLength { min_length: 0.1 };
+        assert_eq!(min_stroke_length.apply(&with_tap), square(0.0));
+        assert_eq!(min_stroke_length.apply(&tap), tap);
+
+        // Templates and candidates go through the same steps.
+        let config = QDollarConfig {
+            preprocessing: vec![Preprocess::Dedupe { min_distance: 0.0 }, min_stroke_length],
+            ..QDollarConfig::default()
+        };
+        let cloud = |points: Vec<Point>| PointCloud::with_config(String::new(), points, &config);
+        assert_eq!(cloud(with_tap.clone()).points, cloud(square(0.0)).points);
+        let mut recognizer = QDollarRecognizer::with_config(config.clone());
+        recognizer.add_gesture("square".to_string(), with_tap).unwrap();
+        recognizer.add_gesture("triangle".to_string(), triangle(0.0)).unwrap();
+        let result = recognizer.recognize(&square(0.0)).unwrap();
+        assert_eq!(result.name, "square");
+        assert!(result.score > 0.99);
+    }
+
+    #[test]
+    fn test_compiled_recognizer() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CompiledRecognizer>();
+
+        let zig = |skew: f64| -> Vec<Point> {
+            [(0.0, 1.0), (1.0 + skew, 1.0), (0.0, 0.0), (1.0, 0.0)]
+                .iter()
+                .map(|&(x, y)| Point::new(x, y, 1))
+                .collect()
+        };
+        let samples = [square(0.1), triangle(0.1), zig(0.1), square(-0.2), zig(0.3)];
+        for config in [
+            QDollarConfig::default(),
+            QDollarConfig {
+                precision: Precision::Grid,
+                stroke_count: StrokeCount::Penalty { per_stroke: 1.0 },
+                ..QDollarConfig::default()
+            },
+            QDollarConfig {
+                prefilter: Prefilter::Nearest { k: 2 },
+                ..QDollarConfig::default()
+            },
+        ] {
+            let mut recognizer = QDollarRecognizer::with_config(config);
+            for (name, points) in [("square", square(0.0)), ("triangle", triangle(0.0))] {
+                recognizer.add_gesture(name.to_string(), points).unwrap();
+            }
+            recognizer.add_gesture("zig".to_string(), zig(0.0)).unwrap();
+            let compiled = recognizer.compile().unwrap();
+            assert_eq!(compiled.len(), 3);
+            for sample in &samples {
+                let expected = recognizer.recognize_n_best(sample, 3).unwrap();
+                let actual = compiled.recognize_n_best(sample, 3).unwrap();
+                assert_eq!(actual.len(), expected.len());
+                for (actual, expected) in actual.iter().zip(&expected) {
+                    assert_eq!(actual.name, expected.name);
+                    assert!((actual.score - expected.score).abs() < 1e-3);
+                }
+            }
+            assert_eq!(compiled.recognize(&[]).err(), Some(QDollarError::EmptyGesture));
+            assert_eq!(
+                compiled.recognize_n_best(&[], 3).err(),
+                Some(QDollarError::EmptyGesture)
+            );
+        }
+
+        // Readers keep their snapshot while a new one is built and swapped in.
+        let mut builder = CompiledRecognizer::builder(QDollarConfig::default());
+        builder
+            .add_gesture("shapes/square".to_string(), square(0.0))
+            .add_gesture("shapes/triangle".to_string(), triangle(0.0))
+            .add_gesture("zig".to_string(), zig(0.0));
+        let current = std::sync::RwLock::new(builder.build().unwrap());
+        let old = current.read().unwrap().clone();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let reader = current.read().unwrap().clone();
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        let result = reader.recognize(&triangle(0.1)).unwrap();
+                        assert_eq!(result.name, "shapes/triangle");
+                    }
+                });
+            }
+            assert_eq!(builder.remove_class("shapes"), 2);
+            *current.write().unwrap() = builder.build().unwrap();
+        });
+        assert!(!old.ptr_eq(&current.read().unwrap()));
+        assert_eq!(old.len(), 3);
+        assert_eq!(current.read().unwrap().recognize(&triangle(0.1)).unwrap().name, "zig");
+
+        let empty = CompiledRecognizer::builder(QDollarConfig::default()).build().unwrap();
+        assert_eq!(
+            empty.recognize(&square(0.0)).unwrap_err(),
+            QDollarError::NoRegisteredGestures
+        );
+        let optimal = QDollarConfig {
+            matching: Matching::Optimal,
+            ..QDollarConfig::default()
+        };
+        assert!(matches!(
+            CompiledRecognizer::builder(optimal).build(),
+            Err(QDollarError::Unsupported(_))
+        ));
+    }
+
     #[test]
     fn test_utils_scale() {
         let points = vec![
@@ -274,7 +1442,9 @@ mod tests {
             Point::new(1.0, 0.0, 1),
             Point::new(0.0, 0.0, 1),
         ];
-        let num_gestures = recognizer.add_gesture("circle".to_string(), circle_points.clone());
+        let num_gestures = recognizer
+            .add_gesture("circle".to_string(), circle_points.clone())
+            .unwrap();
         assert_eq!(num_gestures, 1);
 
         let result = recognizer.recognize(&circle_points).unwrap();
@@ -285,6 +1455,562 @@ mod tests {
         assert_eq!(num_gestures, 0);
     }
 
+    fn square(skew: f64) -> Vec<Point> {
+        vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(0.0, 1.0, 1),
+            Point::new(1.0 + skew, 1.0, 1),
+            Point::new(1.0, 0.0, 1),
+            Point::new(0.0, 0.0, 1),
+        ]
+    }
+
+    fn triangle(skew: f64) -> Vec<Point> {
+        vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(0.5 + skew, 1.0, 1),
+            Point::new(1.0, 0.0, 1),
+            Point::new(0.0, 0.0, 1),
+        ]
+    }
+
+    #[test]
+    fn test_learn_append() {
+        let mut recognizer = QDollarRecognizer::new();
+        assert_eq!(
+            recognizer.learn(&square(0.0), "square").unwrap(),
+            LearnOutcome::Appended { index: 0 }
+        );
+        assert_eq!(
+            recognizer.learn(&triangle(0.0), "triangle").unwrap(),
+            LearnOutcome::Appended { index: 1 }
+        );
+        assert_eq!(
+            recognizer.learn(&square(0.1), "square").unwrap(),
+            LearnOutcome::Appended { index: 2 }
+        );
+        assert_eq!(recognizer.user_gesture_stats()[0].hits, 1);
+        assert_eq!(recognizer.recognize(&square(0.05)).unwrap().name, "square");
+    }
+
+    #[test]
+    fn test_learn_replace_most_confused() {
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            learning_policy: LearningPolicy::ReplaceMostConfused,
+            ..Default::default()
+        });
+        recognizer.add_gesture("triangle".to_string(), triangle(0.4)).unwrap();
+        recognizer.add_gesture("triangle".to_string(), triangle(0.5)).unwrap();
+        recognizer.add_gesture("wedge".to_string(), triangle(-0.3)).unwrap();
+        recognizer.add_gesture("wedge".to_string(), triangle(0.05)).unwrap();
+
+        assert_eq!(recognizer.learn(&triangle(0.05), "wedge").unwrap(), LearnOutcome::Unchanged);
+        assert_eq!(recognizer.user_gesture_stats()[3].hits, 1);
+        // The closest wedge template wins over both triangles, so it is the
+        // one replaced, and the triangle class keeps its templates.
+        assert_eq!(
+            recognizer.learn(&triangle(0.0), "triangle").unwrap(),
+            LearnOutcome::Replaced { index: 3 }
+        );
+        let names: Vec<&str> = recognizer
+            .user_gestures()
+            .iter()
+            .map(|cloud| cloud.name.as_str())
+            .collect();
+        assert_eq!(names, ["triangle", "triangle", "wedge", "triangle"]);
+        assert_eq!(recognizer.user_gesture_stats()[3], TemplateStats::default());
+        assert_eq!(recognizer.recognize(&triangle(0.0)).unwrap().name, "triangle");
+        assert_eq!(recognizer.recognize(&triangle(-0.3)).unwrap().name, "wedge");
+    }
+
+    #[test]
+    fn test_learn_replace_most_confused_keeps_last_template() {
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            learning_policy: LearningPolicy::ReplaceMostConfused,
+            ..Default::default()
+        });
+        recognizer.add_gesture("wedge".to_string(), triangle(0.05)).unwrap();
+        recognizer.add_gesture("square".to_string(), square(0.0)).unwrap();
+
+        // The only wedge template wins, but replacing it would delete the
+        // class, so the sample is appended instead.
+        assert_eq!(
+            recognizer.learn(&triangle(0.0), "triangle").unwrap(),
+            LearnOutcome::Appended { index: 2 }
+        );
+        let names: Vec<&str> = recognizer
+            .user_gestures()
+            .iter()
+            .map(|cloud| cloud.name.as_str())
+            .collect();
+        assert_eq!(names, ["wedge", "square", "triangle"]);
+        assert_eq!(recognizer.user_gesture_stats()[0].confusions, 1);
+    }
+
+    #[test]
+    fn test_learn_cap_per_class() {
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            learning_policy: LearningPolicy::CapPerClass { max_templates: 2 },
+            ..Default::default()
+        });
+        recognizer.add_gesture("triangle".to_string(), triangle(0.0)).unwrap();
+        recognizer.learn(&square(0.0), "square").unwrap();
+        recognizer.learn(&square(0.1), "square").unwrap();
+        // The second square template is the best match for this mislabelled
+        // sample, which makes it the least useful one.
+        recognizer.learn(&square(0.2), "triangle").unwrap();
+        let outcome = recognizer.learn(&square(0.05), "square").unwrap();
+
+        assert_eq!(outcome, LearnOutcome::Evicted { index: 2 });
+        let squares = recognizer
+            .user_gestures()
+            .iter()
+            .filter(|cloud| cloud.name == "square")
+            .count();
+        assert_eq!(squares, 2);
+    }
+
+    #[test]
+    fn test_user_gestures_layered_over_base() {
+        let base = std::sync::Arc::new(vec![
+            PointCloud::new("square".to_string(), square(0.0)),
+            PointCloud::new("triangle".to_string(), triangle(0.0)),
+        ]);
+
+        let mut recognizer = QDollarRecognizer::new();
+        recognizer.set_base_gestures(base.clone());
+        assert_eq!(recognizer.recognize(&square(0.1)).unwrap().name, "square");
+
+        // User templates are searched alongside the base set.
+        recognizer.add_gesture("box".to_string(), square(0.1)).unwrap();
+        assert_eq!(recognizer.recognize(&square(0.1)).unwrap().name, "box");
+        assert_eq!(recognizer.delete_user_gestures(), 0);
+        assert_eq!(recognizer.base_gestures().len(), 2);
+
+        // With shadowing, a user class replaces the base class of that name.
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            user_shadows_base: true,
+            ..Default::default()
+        });
+        recognizer.set_base_gestures(base);
+        recognizer.add_gesture("triangle".to_string(), triangle(0.3)).unwrap();
+        let result = recognizer.recognize(&triangle(0.0)).unwrap();
+        assert_eq!(result.name, "triangle");
+        assert!(result.score < 1.0);
+    }
+
+    fn skewed_set(name: &str, shape: fn(f64) -> Vec<Point>, skews: &[f64]) -> Vec<PointCloud> {
+        skews
+            .iter()
+            .map(|&skew| PointCloud::new(name.to_string(), shape(skew)))
+            .collect()
+    }
+
+    #[test]
+    fn test_prune_condensed_nearest_neighbour() {
+        let skews = [0.0, 0.02, 0.04, 0.06, 0.08, 0.1];
+        let mut templates = skewed_set("square", square, &skews);
+        templates.extend(skewed_set("triangle", triangle, &skews));
+        let validation = [
+            skewed_set("square", square, &[0.01, 0.05]),
+            skewed_set("triangle", triangle, &[0.03, 0.09]),
+        ]
+        .concat();
+
+        let config = QDollarConfig::default();
+        let pruned = prune::condensed_nearest_neighbour(&config, &templates);
+        let report = prune::report(&config, &templates, &pruned, &validation);
+        assert!(report.pruned_size < report.original_size);
+        assert_eq!(report.pruned_accuracy, 1.0);
+        assert_eq!(report.original_accuracy, 1.0);
+
+        // A template no distance can be computed for is kept, as nothing
+        // classifies it.
+        let broken = PointCloud::new("square".to_string(), vec![Point::new(f64::NAN, 0.0, 1); 4]);
+        assert_eq!(prune::nearest(&config, &broken, &templates), None);
+        let with_broken = [templates.clone(), vec![broken]].concat();
+        let pruned = prune::condensed_nearest_neighbour(&config, &with_broken);
+        assert!(pruned.iter().any(|t| t.points[0].x.is_nan()));
+
+        // Distances follow the recognizer's configuration.
+        let mut points = square(0.0);
+        points.extend(triangle(0.0).iter().map(|pt| Point::new(pt.x, pt.y, 2)));
+        let two_strokes = PointCloud::new("square".to_string(), points);
+        let strict = QDollarConfig {
+            stroke_count: StrokeCount::Filter { max_difference: 0 },
+            ..Default::default()
+        };
+        assert!(prune::nearest(&config, &two_strokes, &templates).is_some());
+        assert_eq!(prune::nearest(&strict, &two_strokes, &templates), None);
+    }
+
+    #[test]
+    fn test_seeded_runs_are_reproducible() {
+        use crate::rng::{Rng, SplitMix64};
+
+        let draws = |seed| {
+            let mut rng = SplitMix64::new(seed);
+            (0..16).map(|_| rng.below(10)).collect::<Vec<_>>()
+        };
+        assert_eq!(draws(7), draws(7));
+        assert_ne!(draws(7), draws(8));
+        assert!(draws(7).iter().all(|&i| i < 10));
+        let mut items: Vec<usize> = (0..20).collect();
+        rng::shuffle(&mut items, &mut SplitMix64::new(3));
+        assert_ne!(items, (0..20).collect::<Vec<_>>());
+        items.sort_unstable();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+
+        let mut samples = Vec::new();
+        for skew in [0.0, 0.05, 0.1, 0.15, 0.2] {
+            samples.push(eval::Sample::new("square".to_string(), square(skew)));
+            samples.push(eval::Sample::new("triangle".to_string(), triangle(skew)));
+        }
+        let names = |samples: &[eval::Sample]| -> Vec<String> {
+            samples.iter().map(|s| format!("{}{:?}", s.name, s.points[2])).collect()
+        };
+        let (templates, tests) = eval::split(&samples, 2, 11);
+        assert_eq!(templates.len(), 4);
+        assert_eq!(tests.len(), 6);
+        assert_eq!(templates.iter().filter(|s| s.name == "square").count(), 2);
+        let (again, _) = eval::split(&samples, 2, 11);
+        assert_eq!(names(&templates), names(&again));
+
+        let config = QDollarConfig::default();
+        let report = eval::evaluate_split(&config, &samples, 2, 11);
+        assert_eq!(report.seed, Some(11));
+        let rerun = eval::evaluate_split(&config, &samples, 2, report.seed.unwrap());
+        assert_eq!((rerun.correct, rerun.total), (report.correct, report.total));
+        assert_eq!(eval::evaluate_config(&config, &templates, &tests).seed, None);
+
+        let mut templates = skewed_set("square", square, &[0.0, 0.04, 0.08, 0.3]);
+        templates.extend(skewed_set("triangle", triangle, &[0.0, 0.04, 0.08, 0.3]));
+        let pruned_names = |pruned: Vec<PointCloud>| -> Vec<String> {
+            pruned.iter().map(|t| format!("{}{:?}", t.name, t.points[2])).collect()
+        };
+        assert_eq!(
+            pruned_names(prune::condensed_nearest_neighbour_seeded(&config, &templates, 5)),
+            pruned_names(prune::condensed_nearest_neighbour_seeded(&config, &templates, 5))
+        );
+    }
+
+    #[test]
+    fn test_prune_k_medoids() {
+        let config = QDollarConfig::default();
+        let skews = [0.0, 0.1, 0.2, 0.3];
+        let mut templates = skewed_set("square", square, &skews);
+        templates.extend(skewed_set("triangle", triangle, &skews));
+
+        let pruned = prune::k_medoids(&config, &templates, 1);
+        assert_eq!(pruned.len(), 2);
+        assert_eq!(pruned[0].name, "square");
+        assert_eq!(pruned[1].name, "triangle");
+        // The medoid of an evenly spread class sits in the middle.
+        let middle = PointCloud::new("square".to_string(), square(0.1));
+        assert_eq!(utils::cloud_match(&pruned[0], &middle, f64::INFINITY), 0.0);
+
+        assert_eq!(prune::k_medoids(&config, &templates, 10).len(), templates.len());
+
+        let tradeoff = prune::tradeoff(&config, &templates, &templates, 3);
+        let sizes: Vec<usize> = tradeoff.iter().map(|point| point.size).collect();
+        assert_eq!(sizes, vec![2, 4, 6]);
+        assert!(tradeoff.iter().all(|point| point.accuracy == 1.0));
+    }
+
+    #[test]
+    fn test_prune_remove_misleading() {
+        let config = QDollarConfig::default();
+        let mut templates = skewed_set("square", square, &[0.0, 0.1]);
+        templates.extend(skewed_set("triangle", triangle, &[0.0]));
+        // A triangle that was saved under the wrong name.
+        templates.extend(skewed_set("square", triangle, &[0.2]));
+        let validation = skewed_set("triangle", triangle, &[0.15, 0.2, 0.25]);
+
+        let pruned = prune::remove_misleading(&config, &templates, &validation, 2);
+        assert_eq!(pruned.len(), 3);
+        assert!(prune::accuracy(&config, &pruned, &validation)
+            > prune::accuracy(&config, &templates, &validation));
+
+        // Not enough evidence against it yet.
+        assert_eq!(prune::remove_misleading(&config, &templates, &validation, 4).len(), 4);
+    }
+
+    #[test]
+    fn test_analysis_flags_colliding_classes() {
+        let mut templates = skewed_set("square", square, &[0.0, 0.1, 0.2]);
+        templates.extend(skewed_set("triangle", triangle, &[0.0, 0.1, 0.2]));
+        let config = QDollarConfig::default();
+        let report = analysis::analyze(&config, &templates, 1.5);
+        assert_eq!(report.matrix.classes, vec!["square", "triangle"]);
+        assert!(report.matrix.distances[0][1] > report.matrix.distances[0][0]);
+        assert!(report.collisions.is_empty());
+
+        let proposed = skewed_set("box", square, &[0.12, 0.3]);
+        let collisions = analysis::vet_gesture(&config, &templates, &proposed, 1.5);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].class_a, "square");
+        assert_eq!(collisions[0].class_b, "box");
+        // square(0.1) and box(0.12) are the closest pair.
+        assert_eq!((collisions[0].template_a, collisions[0].template_b), (1, 6));
+    }
+
+    #[test]
+    fn test_analysis_export() {
+        let mut templates = skewed_set("square", square, &[0.0, 0.1]);
+        templates.extend(skewed_set("box, wide", square, &[0.05]));
+        let report = analysis::analyze(&QDollarConfig::default(), &templates, 1.5);
+        assert_eq!(report.collisions.len(), 1);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["matrix"]["classes"][1], "box, wide");
+        assert_eq!(json["collisions"][0]["class_a"], "square");
+
+        let matrix = report.matrix_csv();
+        assert_eq!(matrix.lines().next(), Some("class,square,\"box, wide\""));
+        assert_eq!(matrix.lines().count(), 3);
+        let collisions = report.collisions_csv();
+        assert!(collisions.starts_with("class_a,class_b,distance"));
+        assert!(collisions.lines().nth(1).unwrap().starts_with("square,\"box, wide\","));
+        assert_eq!(report.collisions_with("box, wide").count(), 1);
+    }
+
+    #[test]
+    fn test_analysis_single_template_classes() {
+        let config = QDollarConfig::default();
+        let mut templates = skewed_set("square", square, &[0.0]);
+        templates.extend(skewed_set("box", square, &[0.1]));
+        templates.extend(skewed_set("triangle", triangle, &[0.0]));
+        templates.extend(skewed_set("copy", square, &[0.0]));
+        let report = analysis::analyze(&config, &templates, 1.5);
+
+        // No class has a spread, so half the mean distance between classes
+        // stands in for it.
+        let pairs: Vec<(&str, &str)> = report
+            .collisions
+            .iter()
+            .map(|c| (c.class_a.as_str(), c.class_b.as_str()))
+            .collect();
+        assert_eq!(pairs, [("square", "copy"), ("square", "box"), ("box", "copy")]);
+        let d = &report.matrix.distances;
+        let between = d[0][1] + d[0][2] + d[0][3] + d[1][2] + d[1][3] + d[2][3];
+        assert_eq!(report.collisions[0].spread, 0.5 * between / 6.0);
+        assert_eq!(report.collisions[0].ratio, 0.0);
+        assert!(report.matrix.distances.iter().flatten().all(|d| d.is_finite()));
+        assert!(!report.to_json().unwrap().contains("null"));
+        assert!(!report.collisions_csv().contains("NaN"));
+
+        // A new one-sample gesture is measured against the mean spread of the
+        // classes that have one.
+        let mut templates = skewed_set("square", square, &[0.0]);
+        templates.extend(skewed_set("triangle", triangle, &[0.0, 0.3]));
+        let proposed = skewed_set("box", square, &[0.1]);
+        let collisions = analysis::vet_gesture(&config, &templates, &proposed, 1.5);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].class_a, "square");
+        assert_eq!(collisions[0].class_b, "box");
+        let triangle_spread = analysis::analyze(&config, &templates, 1.5).matrix.distances[1][1];
+        assert_eq!(collisions[0].spread, triangle_spread);
+    }
+
+    #[test]
+    fn test_recognize_stats() {
+        let mut recognizer = QDollarRecognizer::new();
+        recognizer.add_gesture("square".to_string(), square(0.0)).unwrap();
+        assert!(recognizer.recognize(&square(0.0)).unwrap().stats.is_none());
+
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            collect_stats: true,
+            ..Default::default()
+        });
+        recognizer.add_gesture("square".to_string(), square(0.0)).unwrap();
+        for skew in [0.1, 0.2, 0.3] {
+            recognizer.add_gesture("square".to_string(), square(skew)).unwrap();
+            recognizer.add_gesture("triangle".to_string(), triangle(skew)).unwrap();
+        }
+
+        let result = recognizer.recognize(&square(0.0)).unwrap();
+        let stats = result.stats.unwrap();
+        assert_eq!(stats.templates, 7);
+        assert!(stats.templates_evaluated >= 1);
+        // Seven start indices in each direction for 32 points.
+        let starts = 7 * 2 * stats.templates;
+        assert_eq!(stats.counters.distance_calls + stats.counters.starts_skipped, starts);
+        assert!(stats.counters.starts_skipped > 0);
+        assert!(stats.counters.abandoned <= stats.counters.distance_calls);
+        assert!(stats.normalization_time + stats.matching_time <= result.time);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut recognizer = QDollarRecognizer::new();
+        recognizer.add_gesture("square".to_string(), square(0.0)).unwrap();
+        recognizer.add_gesture("triangle".to_string(), triangle(0.0)).unwrap();
+
+        let path = std::env::temp_dir().join(format!("qdollar-{}.json", std::process::id()));
+        recognizer.save(&path).unwrap();
+        let mut loaded = QDollarRecognizer::new();
+        assert_eq!(loaded.load(&path).unwrap(), 2);
+        std::fs::remove_file(&path).unwrap();
+
+        for (original, restored) in recognizer.user_gestures().iter().zip(loaded.user_gestures()) {
+            assert_eq!(original.name, restored.name);
+            assert_eq!(original.points, restored.points);
+            assert_eq!(original.lut, restored.lut);
+        }
+        assert_eq!(loaded.recognize(&triangle(0.1)).unwrap().name, "triangle");
+
+        assert!(matches!(loaded.load(&path), Err(QDollarError::Io(_))));
+    }
+
+    #[test]
+    fn test_load_json_rejects_bad_input() {
+        let mut recognizer = QDollarRecognizer::new();
+        assert!(matches!(
+            recognizer.load_json("not json"),
+            Err(QDollarError::Serialization(_))
+        ));
+        assert!(matches!(
+            recognizer.load_json(r#"{"version": 99, "templates": []}"#),
+            Err(QDollarError::Serialization(_))
+        ));
+        assert!(matches!(
+            recognizer.load_json(
+                r#"{"version": 1, "templates": [{"name": "dot", "points": [{"x": 0, "y": 0, "id": 1}]}]}"#
+            ),
+            Err(QDollarError::InvalidTemplate(_))
+        ));
+        assert_eq!(recognizer.load_json(r#"{"version": 1, "templates": []}"#), Ok(0));
+    }
+
+    #[test]
+    fn test_recognize_n_best() {
+        let mut recognizer = QDollarRecognizer::new();
+        let mut templates = skewed_set("square", square, &[0.0, 0.3, 0.6]);
+        templates.extend(skewed_set("triangle", triangle, &[0.0, 0.2]));
+        templates.push(PointCloud::new(
+            "line".to_string(),
+            vec![Point::new(0.0, 0.0, 1), Point::new(1.0, 1.0, 1)],
+        ));
+        recognizer.replace_user_gestures(templates.clone());
+
+        let candidate_points = square(0.1);
+        let candidate = PointCloud::new(String::new(), candidate_points.clone());
+        let mut expected: Vec<(String, f64)> = Vec::new();
+        for template in &templates {
+            let dist = utils::cloud_match(&candidate, template, f64::INFINITY);
+            match expected.iter_mut().find(|(name, _)| *name == template.name) {
+                Some(entry) => entry.1 = entry.1.min(dist),
+                None => expected.push((template.name.clone(), dist)),
+            }
+        }
+        expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let results = recognizer.recognize_n_best(&candidate_points, 5).unwrap();
+        assert_eq!(results.len(), 3);
+        for (result, (name, dist)) in results.iter().zip(&expected) {
+            assert_eq!(&result.name, name);
+            assert!((result.score - 1.0 / dist.max(1.0)).abs() < 1e-12);
+        }
+
+        let best = recognizer.recognize(&candidate_points).unwrap();
+        assert_eq!(best.name, results[0].name);
+        assert_eq!(best.score, results[0].score);
+        assert_eq!(recognizer.recognize_n_best(&candidate_points, 2).unwrap().len(), 2);
+        assert!(recognizer.recognize_n_best(&candidate_points, 0).unwrap().is_empty());
+        assert!(QDollarRecognizer::new().recognize_n_best(&candidate_points, 0).is_err());
+    }
+
+    #[test]
+    fn test_custom_clock() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::time::Duration;
+
+        // Advances one millisecond every time it is read.
+        struct TickClock(AtomicU64);
+        impl Clock for TickClock {
+            fn now(&self) -> Duration {
+                Duration::from_millis(self.0.fetch_add(1, Ordering::Relaxed))
+            }
+        }
+
+        let mut recognizer = QDollarRecognizer::with_config(QDollarConfig {
+            collect_stats: true,
+            ..QDollarConfig::default()
+        });
+        recognizer.add_gesture("square".to_string(), square(0.0)).unwrap();
+        recognizer.set_clock(std::sync::Arc::new(TickClock(AtomicU64::new(0))));
+        let result = recognizer.recognize(&square(0.1)).unwrap();
+        assert_eq!(result.time, Duration::from_millis(2));
+        let stats = result.stats.unwrap();
+        assert_eq!(stats.normalization_time, Duration::from_millis(1));
+        assert_eq!(stats.matching_time, Duration::from_millis(1));
+
+        recognizer.set_clock(std::sync::Arc::new(clock::NoClock));
+        assert_eq!(recognizer.recognize(&square(0.1)).unwrap().time, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_precision_accuracy() {
+        let skews = [-0.3, -0.15, 0.0, 0.15, 0.3];
+        let mut templates = Vec::new();
+        let mut samples = Vec::new();
+        for (name, shape) in [("square", square as fn(f64) -> Vec<Point>), ("triangle", triangle)] {
+            for &skew in &skews {
+                templates.push(eval::Sample::new(name.to_string(), shape(skew)));
+                samples.push(eval::Sample::new(name.to_string(), shape(skew + 0.07)));
+            }
+        }
+
+        let report = |precision| {
+            let config = QDollarConfig {
+                precision,
+                ..QDollarConfig::default()
+            };
+            eval::evaluate_config(&config, &templates, &samples)
+        };
+        let baseline = report(Precision::F64);
+        assert_eq!(baseline.accuracy, 1.0);
+        assert_eq!(report(Precision::F32).accuracy, baseline.accuracy);
+        assert_eq!(report(Precision::Grid).accuracy, baseline.accuracy);
+
+        // Grid quantization is 1/1023 of the gesture size. Greedy matching can
+        // amplify it when a nearest-point choice flips, so bound the mean error.
+        let clouds: Vec<PointCloud> = templates
+            .iter()
+            .chain(&samples)
+            .map(|s| PointCloud::new(s.name.clone(), s.points.clone()))
+            .collect();
+        let mut total_error = 0.0;
+        let mut pairs = 0;
+        for a in &clouds {
+            for b in &clouds {
+                let mut counters = utils::MatchCounters::default();
+                let exact = utils::cloud_match_as::<f64>(a, b, f64::INFINITY, &mut counters);
+                let single = utils::cloud_match_as::<f32>(a, b, f64::INFINITY, &mut counters);
+                let grid = utils::cloud_match_as::<i64>(a, b, f64::INFINITY, &mut counters);
+                assert!((single - exact).abs() <= 1e-5 * exact + 1e-9);
+                if exact > 0.0 {
+                    total_error += (grid - exact).abs() / exact;
+                    pairs += 1;
+                }
+            }
+        }
+        assert!(total_error / (pairs as f64) < 0.02);
+    }
+
+    #[test]
+    fn test_precision_coords_are_stored() {
+        use crate::scalar::{Coords, Scalar};
+
+        // Matching borrows each precision's coordinates from the cloud rather
+        // than converting them on every call.
+        let cloud = PointCloud::new("square".to_string(), square(0.0));
+        assert!(core::ptr::eq(f32::cloud_coords(&cloud), f32::cloud_coords(&cloud)));
+        assert_eq!(*f32::cloud_coords(&cloud), Coords::<f32>::new(&cloud.points));
+        assert_eq!(*i64::cloud_coords(&cloud), Coords::<i64>::new(&cloud.points));
+        assert_eq!(f64::cloud_coords(&cloud), cloud.coords());
+    }
+
     #[test]
     fn test_cloud_match() {
         let points1 = vec![
@@ -308,6 +2034,204 @@ mod tests {
         assert!(distance < 0.1);
     }
 
+    #[test]
+    fn test_explain_match() {
+        let template = PointCloud::new("square".to_string(), square(0.0));
+        let candidate = PointCloud::new("square".to_string(), square(0.2));
+
+        let explanation = explain::explain_match(&candidate, &template);
+        assert_eq!(explanation.pairs.len(), utils::NUM_POINTS);
+        let mut matched: Vec<usize> = explanation.pairs.iter().map(|p| p.template_index).collect();
+        matched.sort_unstable();
+        matched.dedup();
+        assert_eq!(matched.len(), utils::NUM_POINTS);
+
+        // The contributions add up to the distance cloud_match reports.
+        let total: f64 = explanation.pairs.iter().map(|p| p.contribution).sum();
+        assert!((total - explanation.distance).abs() < 1e-9);
+        let distance = utils::cloud_match(&candidate, &template, f64::INFINITY);
+        assert!((distance - explanation.distance).abs() < 1e-9);
+        assert_eq!(explanation.pairs[0].weight, utils::NUM_POINTS);
+        assert_eq!(explanation.start % 5, 0);
+
+        let identical = explain::explain_match(&template, &template);
+        assert_eq!(identical.distance, 0.0);
+        assert!(identical
+            .pairs
+            .iter()
+            .all(|p| p.candidate_index == p.template_index));
+    }
+
+    #[test]
+    fn test_recognizer_explain() {
+        let mut recognizer = QDollarRecognizer::new();
+        recognizer.add_gesture("square".to_string(), square(0.0)).unwrap();
+        recognizer.add_gesture("triangle".to_string(), triangle(0.0)).unwrap();
+        recognizer.add_gesture("triangle".to_string(), triangle(0.1)).unwrap();
+
+        let explanation = recognizer.explain(&square(0.1)).unwrap();
+        let result = recognizer.recognize(&square(0.1)).unwrap();
+        assert_eq!(explanation.name, result.name);
+        assert_eq!(explanation.score, result.score);
+        assert_eq!(explanation.best_template, 0);
+        assert_eq!(explanation.templates.len(), 3);
+        assert_eq!(explanation.templates[0].status, explain::TemplateStatus::Evaluated);
+        for evaluation in &explanation.templates[1..] {
+            assert_ne!(evaluation.status, explain::TemplateStatus::Evaluated);
+            assert_eq!(
+                evaluation.distance.is_none(),
+                evaluation.status == explain::TemplateStatus::Pruned
+            );
+        }
+        assert_eq!(explanation.alignment.pairs.len(), utils::NUM_POINTS);
+
+        assert_eq!(
+            QDollarRecognizer::new().explain(&square(0.0)).unwrap_err(),
+            QDollarError::NoRegisteredGestures
+        );
+        assert_eq!(recognizer.explain(&[]).unwrap_err(), QDollarError::EmptyGesture);
+    }
+
+    #[test]
+    fn test_prefilter_large_template_set() {
+        let base: Vec<PointCloud> = (0..400)
+            .map(|i| {
+                let skew = (i / 2) as f64 * 0.0025;
+                if i % 2 == 0 {
+                    PointCloud::new("square".to_string(), square(skew))
+                } else {
+                    PointCloud::new("triangle".to_string(), triangle(skew))
+                }
+            })
+            .collect();
+        let base = std::sync::Arc::new(base);
+
+        let mut exhaustive = QDollarRecognizer::with_config(QDollarConfig {
+            collect_stats: true,
+            ..QDollarConfig::default()
+        });
+        exhaustive.set_base_gestures(base.clone());
+        let mut shortlisted = QDollarRecognizer::with_config(QDollarConfig {
+            collect_stats: true,
+            prefilter: Prefilter::Nearest { k: 32 },
+            ..QDollarConfig::default()
+        });
+        shortlisted.set_base_gestures(base);
+
+        for skew in [0.05, 0.3] {
+            for sample in [square(skew), triangle(skew)] {
+                let expected = exhaustive.recognize(&sample).unwrap();
+                let result = shortlisted.recognize(&sample).unwrap();
+                assert_eq!(result.name, expected.name);
+                assert_eq!(expected.stats.unwrap().templates, 400);
+                assert_eq!(result.stats.unwrap().templates, 32);
+            }
+        }
+
+        let explanation = shortlisted.explain(&square(0.2)).unwrap();
+        assert_eq!(explanation.templates.len(), 400);
+        let filtered = explanation
+            .templates
+            .iter()
+            .filter(|evaluation| evaluation.status == explain::TemplateStatus::Filtered)
+            .count();
+        assert_eq!(filtered, 400 - 32);
+    }
+
+    #[test]
+    fn test_vp_tree_scales_sublinearly() {
+        use crate::index::{Features, VpTree};
+        use crate::rng::{Rng, SplitMix64};
+
+        // Features of squares and triangles of random skew and aspect ratio,
+        // normalized as templates are but without building their LUTs.
+        let features = |n: usize, rng: &mut SplitMix64| -> Vec<Features> {
+            (0..n)
+                .map(|i| {
+                    let shape = if i % 2 == 0 { square } else { triangle };
+                    let aspect = rng.range(0.5, 2.0);
+                    let points: Vec<Point> = shape(rng.range(0.0, 0.5))
+                        .iter()
+                        .map(|pt| Point::new(pt.x * aspect, pt.y, pt.id))
+                        .collect();
+                    let points = utils::scale(&utils::resample(&points, utils::NUM_POINTS));
+                    Features::new(&utils::translate_to(&points, &utils::ORIGIN))
+                })
+                .collect()
+        };
+        let mut rng = SplitMix64::new(40);
+        let queries = features(20, &mut rng);
+        let mut evaluations = |size: usize| {
+            let tree = VpTree::new(features(size, &mut rng));
+            let mut evaluations = 0;
+            for query in &queries {
+                let nearest = tree.nearest_counted(query, 32, |_| true, &mut evaluations);
+                assert_eq!(nearest.len(), 32);
+            }
+            evaluations / queries.len()
+        };
+
+        let small = evaluations(1_000);
+        let large = evaluations(10_000);
+        // Ten times the templates costs far less than ten times the work.
+        assert!(large < 2 * small, "{} then {} evaluations", small, large);
+        assert!(large < 10_000 / 10, "{} evaluations", large);
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_render_svg() {
+        let options = render::SvgOptions::default();
+        let template = PointCloud::new("square".to_string(), square(0.0));
+        let candidate = PointCloud::new("square".to_string(), square(0.2));
+
+        let svg = render::cloud_svg(&template, &options);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), utils::NUM_POINTS);
+        assert_eq!(svg.matches("<rect").count(), utils::LUT_SIZE * utils::LUT_SIZE + 1);
+
+        let no_lut = render::SvgOptions {
+            show_lut: false,
+            ..options
+        };
+        let points = vec![
+            Point::new(0.0, 0.0, 1),
+            Point::new(0.0, 1.0, 1),
+            Point::new(1.0, 0.0, 2),
+            Point::new(1.0, 1.0, 2),
+        ];
+        let svg = render::gesture_svg(&points, &QDollarConfig::default(), &no_lut);
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert_eq!(svg.matches("<rect").count(), 1);
+
+        let svg = render::alignment_svg(&candidate, &template, &no_lut);
+        assert_eq!(svg.matches("<line").count(), utils::NUM_POINTS);
+        assert_eq!(svg.matches("<circle").count(), 2 * utils::NUM_POINTS);
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn test_render_png() {
+        let options = render::SvgOptions {
+            size: 64,
+            ..Default::default()
+        };
+        let template = PointCloud::new("square".to_string(), square(0.0));
+        let candidate = PointCloud::new("square".to_string(), square(0.2));
+
+        let png = render::cloud_png(&template, &options).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // The IHDR chunk follows the signature: width, then height.
+        assert_eq!(&png[16..24], &[0, 0, 0, 64, 0, 0, 0, 64]);
+        let png = render::gesture_png(&square(0.1), &QDollarConfig::default(), &options).unwrap();
+        assert_eq!(&png[..4], b"\x89PNG");
+        let png = render::alignment_png(&candidate, &template, &options).unwrap();
+        assert_eq!(&png[..4], b"\x89PNG");
+
+        assert!(render::svg_to_png("not an svg").is_err());
+    }
+
     #[test]
     fn test_centroid() {
         let points = vec![
@@ -0,0 +1,102 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::PointCloud;
+
+/// Application data attached to a template. None of it affects matching.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateMetadata {
+    /// Who drew the template, such as a user id.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Free-form data for the application, such as a command id.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+}
+
+impl TemplateMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == TemplateMetadata::default()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// Separates the levels of a hierarchical gesture name, as in "letters/a".
+pub const CLASS_SEPARATOR: char = '/';
+
+/// Whether `name` is `class` or lies anywhere below it in the hierarchy.
+/// "letters/a" is in "letters" and in "letters/a", but not in "letter".
+pub fn in_class(name: &str, class: &str) -> bool {
+    match name.strip_prefix(class) {
+        Some(rest) => rest.is_empty() || rest.starts_with(CLASS_SEPARATOR),
+        None => false,
+    }
+}
+
+/// The class one level up, or `None` for a top-level name.
+pub fn parent_class(name: &str) -> Option<&str> {
+    name.rsplit_once(CLASS_SEPARATOR).map(|(parent, _)| parent)
+}
+
+/// The templates a recognition may choose from, for example the gestures
+/// one screen of an app responds to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vocabulary {
+    /// Classes whose templates are allowed, with everything below them in
+    /// the hierarchy. Empty allows every class.
+    pub classes: Vec<String>,
+    /// Templates must carry at least one of these tags. Empty allows
+    /// templates with any tags or none.
+    pub tags: Vec<String>,
+}
+
+impl Vocabulary {
+    /// Allows every template.
+    pub fn all() -> Self {
+        Vocabulary::default()
+    }
+
+    pub fn classes<I, S>(classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Vocabulary {
+            classes: classes.into_iter().map(Into::into).collect(),
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn tags<I, S>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Vocabulary {
+            classes: Vec::new(),
+            tags: tags.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn allows(&self, template: &PointCloud) -> bool {
+        (self.classes.is_empty()
+            || self
+                .classes
+                .iter()
+                .any(|class| in_class(&template.name, class)))
+            && (self.tags.is_empty() || self.tags.iter().any(|tag| template.metadata.has_tag(tag)))
+    }
+}
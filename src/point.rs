@@ -1,9 +1,15 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
     pub id: u32,
+    #[serde(default)]
+    pub time: f64,
+    #[serde(skip)]
     pub int_x: usize,
+    #[serde(skip)]
     pub int_y: usize,
 }
 
@@ -13,8 +19,16 @@ impl Point {
             x,
             y,
             id,
+            time: 0.0,
             int_x: 0,
             int_y: 0,
         }
     }
+
+    pub fn with_time(x: f64, y: f64, id: u32, time: f64) -> Self {
+        Point {
+            time,
+            ..Point::new(x, y, id)
+        }
+    }
 }
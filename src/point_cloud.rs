@@ -1,19 +1,111 @@
-use crate::{utils, Point};
+use alloc::string::String;
+use alloc::vec::Vec;
 
+use crate::error::QDollarError;
+use crate::index::Features;
+use crate::metadata::TemplateMetadata;
+use crate::scalar::Coords;
+use crate::{utils, Point, QDollarConfig};
+
+#[derive(Debug, Clone)]
 pub struct PointCloud {
     pub name: String,
     pub points: Vec<Point>,
     pub lut: Vec<Vec<usize>>,
+    pub metadata: TemplateMetadata,
+    coords: Coords<f64>,
+    // The same coordinates for `Precision::F32` and `Precision::Grid`, so
+    // matching borrows them instead of converting every cloud per match.
+    coords_f32: Coords<f32>,
+    coords_grid: Coords<i64>,
+    features: Features,
+    stroke_count: usize,
 }
 
 impl PointCloud {
     pub fn new(name: String, points: Vec<Point>) -> Self {
-        let points = utils::resample(&points, utils::NUM_POINTS);
+        Self::with_config(name, points, &QDollarConfig::default())
+    }
+
+    pub fn with_config(name: String, points: Vec<Point>, config: &QDollarConfig) -> Self {
+        let points = config.preprocess(&points);
+        // Counted before resampling, which can drop short strokes like dots.
+        let stroke_count = utils::stroke_count(&points);
+        let points = config
+            .resample_strategy
+            .resample(&points, utils::NUM_POINTS);
         let points = utils::scale(&points);
         let points = utils::translate_to(&points, &utils::ORIGIN);
+        Self::from_normalized(name, points).with_stroke_count(stroke_count)
+    }
+
+    /// Like `with_config`, but fails for a gesture without points, which
+    /// would otherwise give a cloud with no points to match.
+    pub fn try_with_config(
+        name: String,
+        points: Vec<Point>,
+        config: &QDollarConfig,
+    ) -> Result<Self, QDollarError> {
+        if points.is_empty() {
+            return Err(QDollarError::EmptyGesture);
+        }
+        Ok(Self::with_config(name, points, config))
+    }
+
+    pub fn from_normalized(name: String, points: Vec<Point>) -> Self {
         let points = utils::make_int_coords(&points);
         let lut = utils::compute_lut(&points);
 
-        PointCloud { name, points, lut }
+        let coords = Coords::new(&points);
+        let coords_f32 = Coords::new(&points);
+        let coords_grid = Coords::new(&points);
+        let features = Features::new(&points);
+        let stroke_count = utils::stroke_count(&points);
+
+        PointCloud {
+            name,
+            points,
+            lut,
+            metadata: TemplateMetadata::default(),
+            coords,
+            coords_f32,
+            coords_grid,
+            features,
+            stroke_count,
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: TemplateMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Overrides the stroke count, which `from_normalized` can only take from
+    /// the resampled points.
+    pub fn with_stroke_count(mut self, stroke_count: usize) -> Self {
+        self.stroke_count = stroke_count;
+        self
+    }
+
+    /// The points' coordinates laid out as separate x and y arrays.
+    pub fn coords(&self) -> &Coords<f64> {
+        &self.coords
+    }
+
+    pub(crate) fn coords_f32(&self) -> &Coords<f32> {
+        &self.coords_f32
+    }
+
+    pub(crate) fn coords_grid(&self) -> &Coords<i64> {
+        &self.coords_grid
+    }
+
+    pub fn features(&self) -> &Features {
+        &self.features
+    }
+
+    /// Strokes of the gesture as drawn, after preprocessing.
+    pub fn stroke_count(&self) -> usize {
+        self.stroke_count
     }
 }
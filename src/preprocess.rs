@@ -0,0 +1,126 @@
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+use crate::utils;
+use crate::Point;
+
+pub fn dedupe(points: &[Point], min_distance: f64) -> Vec<Point> {
+    let mut kept: Vec<Point> = Vec::with_capacity(points.len());
+    for &pt in points {
+        match kept.last() {
+            Some(last)
+                if last.id == pt.id && utils::euclidean_distance(last, &pt) <= min_distance => {}
+            _ => kept.push(pt),
+        }
+    }
+    kept
+}
+
+pub fn moving_average(points: &[Point], window: usize) -> Vec<Point> {
+    let before = window.saturating_sub(1) / 2;
+    let after = window / 2;
+    strokes(points)
+        .flat_map(|stroke| {
+            (0..stroke.len()).map(move |i| {
+                let neighbours =
+                    &stroke[i.saturating_sub(before)..(i + after + 1).min(stroke.len())];
+                let n = neighbours.len() as f64;
+                Point {
+                    x: neighbours.iter().map(|pt| pt.x).sum::<f64>() / n,
+                    y: neighbours.iter().map(|pt| pt.y).sum::<f64>() / n,
+                    ..stroke[i]
+                }
+            })
+        })
+        .collect()
+}
+
+// The One Euro filter of Casiez, Roussel and Vogel (CHI 2012), run on each
+// axis of each stroke separately.
+pub fn one_euro(points: &[Point], min_cutoff: f64, beta: f64, d_cutoff: f64) -> Vec<Point> {
+    let alpha = |cutoff: f64, dt: f64| {
+        let tau = 1.0 / (2.0 * PI * cutoff);
+        1.0 / (1.0 + tau / dt)
+    };
+    let mut filtered = Vec::with_capacity(points.len());
+    for stroke in strokes(points) {
+        let mut prev = stroke[0];
+        let mut derivative = [0.0, 0.0];
+        filtered.push(prev);
+        for &pt in &stroke[1..] {
+            let dt = if pt.time > prev.time {
+                pt.time - prev.time
+            } else {
+                1.0
+            };
+            let mut next = pt;
+            for (axis, (value, last)) in [(pt.x, prev.x), (pt.y, prev.y)].into_iter().enumerate() {
+                let a = alpha(d_cutoff, dt);
+                derivative[axis] = a * (value - last) / dt + (1.0 - a) * derivative[axis];
+                let a = alpha(min_cutoff + beta * derivative[axis].abs(), dt);
+                let smoothed = a * value + (1.0 - a) * last;
+                if axis == 0 {
+                    next.x = smoothed;
+                } else {
+                    next.y = smoothed;
+                }
+            }
+            filtered.push(next);
+            prev = next;
+        }
+    }
+    filtered
+}
+
+pub fn remove_hooks(points: &[Point], max_fraction: f64, min_angle: f64) -> Vec<Point> {
+    strokes(points)
+        .flat_map(|stroke| {
+            let forward: Vec<&Point> = stroke.iter().collect();
+            let stroke = &stroke[hook_end(&forward, max_fraction, min_angle)..];
+            let backward: Vec<&Point> = stroke.iter().rev().collect();
+            stroke[..stroke.len() - hook_end(&backward, max_fraction, min_angle)].to_vec()
+        })
+        .collect()
+}
+
+// Number of points before the sharpest turn of at least `min_angle` within
+// `max_fraction` of the stroke's length from its start, or 0 if there is none.
+fn hook_end(stroke: &[&Point], max_fraction: f64, min_angle: f64) -> usize {
+    if stroke.len() < 3 {
+        return 0;
+    }
+    let length: f64 = stroke
+        .windows(2)
+        .map(|pair| utils::euclidean_distance(pair[0], pair[1]))
+        .sum();
+
+    let mut walked = 0.0;
+    let mut hook = (0, min_angle);
+    for i in 1..stroke.len() - 1 {
+        walked += utils::euclidean_distance(stroke[i - 1], stroke[i]);
+        if walked > max_fraction * length {
+            break;
+        }
+        let angle = utils::turning_angle(stroke[i - 1], stroke[i], stroke[i + 1]);
+        if angle >= hook.1 {
+            hook = (i, angle);
+        }
+    }
+    hook.0
+}
+
+pub fn min_stroke_length(points: &[Point], min_length: f64) -> Vec<Point> {
+    let longest = strokes(points).map(utils::path_length).fold(0.0, f64::max);
+    strokes(points)
+        .filter(|stroke| {
+            let length = utils::path_length(stroke);
+            length >= min_length || length == longest
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+fn strokes(points: &[Point]) -> impl Iterator<Item = &[Point]> {
+    points.chunk_by(|a, b| a.id == b.id)
+}
@@ -0,0 +1,293 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::distance::CloudDistance;
+use crate::rng::{self, Rng, SplitMix64};
+use crate::utils::MatchCounters;
+use crate::{PointCloud, QDollarConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PruneReport {
+    pub original_size: usize,
+    pub pruned_size: usize,
+    pub original_accuracy: f64,
+    pub pruned_accuracy: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeoffPoint {
+    pub templates_per_class: usize,
+    pub size: usize,
+    pub accuracy: f64,
+}
+
+// Every function takes the recognizer's configuration and compares templates
+// with its distance, so pruning decides the way recognition will.
+
+// The template nearest to `candidate`, or `None` when no distance is below
+// infinity, as when every distance is NaN.
+pub fn nearest(
+    config: &QDollarConfig,
+    candidate: &PointCloud,
+    templates: &[PointCloud],
+) -> Option<(usize, f64)> {
+    let distance = config.distance();
+    let mut best_distance = f64::INFINITY;
+    let mut best_template = None;
+
+    for (i, template) in templates.iter().enumerate() {
+        let dist = distance.distance(
+            candidate,
+            template,
+            best_distance,
+            &mut MatchCounters::default(),
+        );
+        if dist < best_distance {
+            best_distance = dist;
+            best_template = Some(i);
+        }
+    }
+
+    best_template.map(|i| (i, best_distance))
+}
+
+pub fn accuracy(
+    config: &QDollarConfig,
+    templates: &[PointCloud],
+    validation: &[PointCloud],
+) -> f64 {
+    if validation.is_empty() {
+        return 0.0;
+    }
+    let correct = validation
+        .iter()
+        .filter(|sample| {
+            nearest(config, sample, templates)
+                .is_some_and(|(i, _)| templates[i].name == sample.name)
+        })
+        .count();
+    correct as f64 / validation.len() as f64
+}
+
+pub fn report(
+    config: &QDollarConfig,
+    original: &[PointCloud],
+    pruned: &[PointCloud],
+    validation: &[PointCloud],
+) -> PruneReport {
+    PruneReport {
+        original_size: original.len(),
+        pruned_size: pruned.len(),
+        original_accuracy: accuracy(config, original, validation),
+        pruned_accuracy: accuracy(config, pruned, validation),
+    }
+}
+
+// Hart's condensed nearest neighbour, visiting the templates in an order
+// shuffled with `seed`. The result depends on the order, so reruns with other
+// seeds show how stable it is.
+pub fn condensed_nearest_neighbour_seeded(
+    config: &QDollarConfig,
+    templates: &[PointCloud],
+    seed: u64,
+) -> Vec<PointCloud> {
+    condensed_nearest_neighbour_with(config, templates, &mut SplitMix64::new(seed))
+}
+
+pub fn condensed_nearest_neighbour_with<R: Rng + ?Sized>(
+    config: &QDollarConfig,
+    templates: &[PointCloud],
+    rng: &mut R,
+) -> Vec<PointCloud> {
+    let mut shuffled = templates.to_vec();
+    rng::shuffle(&mut shuffled, rng);
+    condensed_nearest_neighbour(config, &shuffled)
+}
+
+// Hart's condensed nearest neighbour: keep only the templates the kept set
+// would otherwise misclassify, or cannot classify at all.
+pub fn condensed_nearest_neighbour(
+    config: &QDollarConfig,
+    templates: &[PointCloud],
+) -> Vec<PointCloud> {
+    let mut kept: Vec<PointCloud> = Vec::new();
+    let mut absorbed = vec![false; templates.len()];
+
+    for (i, template) in templates.iter().enumerate() {
+        if !kept.iter().any(|cloud| cloud.name == template.name) {
+            kept.push(template.clone());
+            absorbed[i] = true;
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (i, template) in templates.iter().enumerate() {
+            if absorbed[i] {
+                continue;
+            }
+            if let Some((best, _)) = nearest(config, template, &kept) {
+                if kept[best].name == template.name {
+                    continue;
+                }
+            }
+            kept.push(template.clone());
+            absorbed[i] = true;
+            changed = true;
+        }
+    }
+
+    kept
+}
+
+pub fn k_medoids(config: &QDollarConfig, templates: &[PointCloud], k: usize) -> Vec<PointCloud> {
+    let mut kept = Vec::new();
+    for name in class_names(templates) {
+        let members: Vec<&PointCloud> = templates.iter().filter(|t| t.name == name).collect();
+        for i in class_medoids(config, &members, k) {
+            kept.push(members[i].clone());
+        }
+    }
+    kept
+}
+
+// Drops templates that are the nearest neighbour of more wrongly than
+// correctly labelled validation samples, keeping at least one per class.
+pub fn remove_misleading(
+    config: &QDollarConfig,
+    templates: &[PointCloud],
+    validation: &[PointCloud],
+    min_errors: usize,
+) -> Vec<PointCloud> {
+    let mut right = vec![0; templates.len()];
+    let mut wrong = vec![0; templates.len()];
+    for sample in validation {
+        if let Some((i, _)) = nearest(config, sample, templates) {
+            if templates[i].name == sample.name {
+                right[i] += 1;
+            } else {
+                wrong[i] += 1;
+            }
+        }
+    }
+
+    let mut remaining: Vec<usize> = (0..templates.len()).collect();
+    let mut order: Vec<usize> = (0..templates.len()).collect();
+    order.sort_by_key(|&i| core::cmp::Reverse(wrong[i] as isize - right[i] as isize));
+    for i in order {
+        if wrong[i] < min_errors || wrong[i] <= right[i] {
+            continue;
+        }
+        let class_size = remaining
+            .iter()
+            .filter(|&&j| templates[j].name == templates[i].name)
+            .count();
+        if class_size > 1 {
+            remaining.retain(|&j| j != i);
+        }
+    }
+
+    remaining
+        .into_iter()
+        .map(|i| templates[i].clone())
+        .collect()
+}
+
+pub fn tradeoff(
+    config: &QDollarConfig,
+    templates: &[PointCloud],
+    validation: &[PointCloud],
+    max_per_class: usize,
+) -> Vec<TradeoffPoint> {
+    (1..=max_per_class)
+        .map(|templates_per_class| {
+            let pruned = k_medoids(config, templates, templates_per_class);
+            TradeoffPoint {
+                templates_per_class,
+                size: pruned.len(),
+                accuracy: accuracy(config, &pruned, validation),
+            }
+        })
+        .collect()
+}
+
+fn class_names(templates: &[PointCloud]) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for template in templates {
+        if !names.contains(&template.name.as_str()) {
+            names.push(&template.name);
+        }
+    }
+    names
+}
+
+fn class_medoids(config: &QDollarConfig, members: &[&PointCloud], k: usize) -> Vec<usize> {
+    let n = members.len();
+    if n <= k {
+        return (0..n).collect();
+    }
+
+    let distance = config.distance();
+    let mut distances = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let d = distance.distance(
+                members[i],
+                members[j],
+                f64::INFINITY,
+                &mut MatchCounters::default(),
+            );
+            distances[i][j] = d;
+            distances[j][i] = d;
+        }
+    }
+    let cost = |medoids: &[usize]| -> f64 {
+        (0..n)
+            .map(|i| {
+                medoids
+                    .iter()
+                    .map(|&m| distances[i][m])
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .sum()
+    };
+
+    // Greedy build, then swap while it lowers the total cost.
+    let mut medoids: Vec<usize> = Vec::with_capacity(k);
+    while medoids.len() < k {
+        let Some(next) = (0..n).filter(|i| !medoids.contains(i)).min_by(|&a, &b| {
+            let with_a = cost(&[medoids.as_slice(), &[a]].concat());
+            let with_b = cost(&[medoids.as_slice(), &[b]].concat());
+            with_a.total_cmp(&with_b)
+        }) else {
+            break;
+        };
+        medoids.push(next);
+    }
+
+    let mut best_cost = cost(&medoids);
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for slot in 0..k {
+            for candidate in 0..n {
+                if medoids.contains(&candidate) {
+                    continue;
+                }
+                let previous = medoids[slot];
+                medoids[slot] = candidate;
+                let new_cost = cost(&medoids);
+                if new_cost < best_cost {
+                    best_cost = new_cost;
+                    improved = true;
+                } else {
+                    medoids[slot] = previous;
+                }
+            }
+        }
+    }
+
+    medoids.sort_unstable();
+    medoids
+}
@@ -0,0 +1,176 @@
+use numpy::{PyReadonlyArray2, PyUntypedArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::error::QDollarError;
+use crate::{Point, QDollarRecognizer, QDollarResult};
+
+use exceptions::QDollarError as PyQDollarError;
+
+// Declared in its own module so the Python-visible name is `QDollarError`.
+mod exceptions {
+    pyo3::create_exception!(qdollar, QDollarError, pyo3::exceptions::PyException);
+}
+
+impl From<QDollarError> for PyErr {
+    fn from(error: QDollarError) -> Self {
+        PyQDollarError::new_err(error.to_string())
+    }
+}
+
+#[pyclass(name = "Point", module = "qdollar")]
+#[derive(Debug, Clone, Copy)]
+pub struct PyPoint {
+    #[pyo3(get, set)]
+    pub x: f64,
+    #[pyo3(get, set)]
+    pub y: f64,
+    #[pyo3(get, set)]
+    pub id: u32,
+}
+
+#[pymethods]
+impl PyPoint {
+    #[new]
+    fn new(x: f64, y: f64, id: u32) -> Self {
+        PyPoint { x, y, id }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Point(x={}, y={}, id={})", self.x, self.y, self.id)
+    }
+}
+
+#[pyclass(name = "QDollarResult", module = "qdollar", frozen)]
+pub struct PyQDollarResult {
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub score: f64,
+    /// Recognition time in seconds.
+    #[pyo3(get)]
+    pub time: f64,
+}
+
+#[pymethods]
+impl PyQDollarResult {
+    fn __repr__(&self) -> String {
+        format!(
+            "QDollarResult(name={:?}, score={}, time={})",
+            self.name, self.score, self.time
+        )
+    }
+}
+
+impl From<QDollarResult> for PyQDollarResult {
+    fn from(result: QDollarResult) -> Self {
+        PyQDollarResult {
+            name: result.name,
+            score: result.score,
+            time: result.time.as_secs_f64(),
+        }
+    }
+}
+
+#[pyclass(name = "QDollarRecognizer", module = "qdollar")]
+pub struct PyQDollarRecognizer {
+    inner: QDollarRecognizer,
+}
+
+#[pymethods]
+impl PyQDollarRecognizer {
+    #[new]
+    fn new() -> Self {
+        PyQDollarRecognizer {
+            inner: QDollarRecognizer::new(),
+        }
+    }
+
+    /// `points` is a float64 array of shape (N, 3) holding x, y and stroke
+    /// id, or a sequence of `Point` or (x, y, id) tuples.
+    fn add_gesture(&mut self, name: String, points: &Bound<'_, PyAny>) -> PyResult<usize> {
+        Ok(self.inner.add_gesture(name, extract_points(points)?)?)
+    }
+
+    fn recognize(&self, points: &Bound<'_, PyAny>) -> PyResult<PyQDollarResult> {
+        let points = extract_points(points)?;
+        Ok(self.inner.recognize(&points)?.into())
+    }
+
+    fn delete_user_gestures(&mut self) -> usize {
+        self.inner.delete_user_gestures()
+    }
+
+    fn save(&self, path: &str) -> PyResult<()> {
+        Ok(self.inner.save(path)?)
+    }
+
+    fn load(&mut self, path: &str) -> PyResult<usize> {
+        Ok(self.inner.load(path)?)
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        Ok(self.inner.to_json()?)
+    }
+
+    fn load_json(&mut self, json: &str) -> PyResult<usize> {
+        Ok(self.inner.load_json(json)?)
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.user_gestures().len()
+    }
+}
+
+fn extract_points(obj: &Bound<'_, PyAny>) -> PyResult<Vec<Point>> {
+    if let Ok(array) = obj.extract::<PyReadonlyArray2<'_, f64>>() {
+        if array.shape()[1] != 3 {
+            return Err(PyValueError::new_err(format!(
+                "expected an array of shape (N, 3), got {:?}",
+                array.shape()
+            )));
+        }
+        return array
+            .as_array()
+            .rows()
+            .into_iter()
+            .map(|row| Ok(Point::new(row[0], row[1], stroke_id(row[2])?)))
+            .collect();
+    }
+
+    if let Ok(points) = obj.extract::<Vec<PyPoint>>() {
+        return Ok(points
+            .into_iter()
+            .map(|pt| Point::new(pt.x, pt.y, pt.id))
+            .collect());
+    }
+
+    let tuples: Vec<(f64, f64, u32)> = obj.extract().map_err(|_| {
+        PyValueError::new_err(
+            "points must be an (N, 3) float64 array or a sequence of Point or (x, y, id)",
+        )
+    })?;
+    Ok(tuples
+        .into_iter()
+        .map(|(x, y, id)| Point::new(x, y, id))
+        .collect())
+}
+
+fn stroke_id(value: f64) -> PyResult<u32> {
+    if value.fract() != 0.0 || value < 0.0 || value > u32::MAX as f64 {
+        return Err(PyValueError::new_err(format!(
+            "stroke id must be a non-negative integer, got {}",
+            value
+        )));
+    }
+    Ok(value as u32)
+}
+
+#[pymodule]
+fn qdollar(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPoint>()?;
+    m.add_class::<PyQDollarResult>()?;
+    m.add_class::<PyQDollarRecognizer>()?;
+    m.add("QDollarError", m.py().get_type::<PyQDollarError>())?;
+    Ok(())
+}
@@ -1,66 +1,660 @@
-use std::time::Duration;
+use alloc::string::{String, ToString};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::path::Path;
 
-use crate::{utils, Point, PointCloud};
+use crate::clock::{self, Clock};
+use crate::compiled::{CompiledRecognizer, CompiledRecognizerBuilder};
+use crate::distance::CloudDistance;
+use crate::index::VpTree;
+use crate::{LearningPolicy, Point, PointCloud, Prefilter, QDollarConfig};
 use crate::error::QDollarError;
+use crate::storage;
+use crate::synth::{self, Synthesizer};
+use crate::explain::{self, Explanation, TemplateEvaluation, TemplateStatus};
+use crate::metadata::{self, TemplateMetadata, Vocabulary};
+use crate::utils::MatchCounters;
 
 pub struct QDollarRecognizer {
+    config: QDollarConfig,
+    base_clouds: Arc<Vec<PointCloud>>,
+    base_index: Arc<VpTree>,
     point_clouds: Vec<PointCloud>,
+    stats: Vec<TemplateStats>,
+    clock: Arc<dyn Clock>,
+    distance: Arc<dyn CloudDistance>,
 }
 
 #[derive(Debug)]
 pub struct QDollarResult {
+    /// The matched template's gesture name, its class.
     pub name: String,
     pub score: f64,
+    pub template: TemplateId,
+    pub metadata: TemplateMetadata,
     pub time: Duration,
+    /// Only collected when `QDollarConfig::collect_stats` is set.
+    pub stats: Option<RecognizeStats>,
+}
+
+/// Which templates a recognition considers. Templates are left out before
+/// any matching, so they cost no time and can never be the result.
+#[derive(Clone, Copy, Default)]
+pub enum TemplateFilter<'a> {
+    #[default]
+    All,
+    /// Only templates the predicate accepts.
+    Allow(&'a dyn Fn(&PointCloud) -> bool),
+    /// Every template except those the predicate accepts.
+    Deny(&'a dyn Fn(&PointCloud) -> bool),
+}
+
+impl TemplateFilter<'_> {
+    pub fn accepts(&self, template: &PointCloud) -> bool {
+        match self {
+            TemplateFilter::All => true,
+            TemplateFilter::Allow(predicate) => predicate(template),
+            TemplateFilter::Deny(predicate) => !predicate(template),
+        }
+    }
+}
+
+impl fmt::Debug for TemplateFilter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateFilter::All => write!(f, "All"),
+            TemplateFilter::Allow(_) => write!(f, "Allow(..)"),
+            TemplateFilter::Deny(_) => write!(f, "Deny(..)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecognizeOptions<'a> {
+    pub filter: TemplateFilter<'a>,
+    /// Weights for how likely each class is in the current context. A
+    /// class's distance is divided by its weight before ranking. Classes are
+    /// looked up by name, then by each parent class, and weigh 1 when not
+    /// found. A weight of zero or less leaves the class out like `filter`.
+    pub priors: BTreeMap<String, f64>,
+    /// Results to return, at most one per class.
+    pub n: usize,
+}
+
+impl Default for RecognizeOptions<'_> {
+    fn default() -> Self {
+        RecognizeOptions {
+            filter: TemplateFilter::All,
+            priors: BTreeMap::new(),
+            n: 1,
+        }
+    }
+}
+
+impl RecognizeOptions<'_> {
+    pub fn prior(&self, name: &str) -> f64 {
+        let mut class = Some(name);
+        while let Some(name) = class {
+            if let Some(&prior) = self.priors.get(name) {
+                return prior;
+            }
+            class = metadata::parent_class(name);
+        }
+        1.0
+    }
+
+    fn accepts(&self, template: &PointCloud) -> bool {
+        self.filter.accepts(template) && self.prior(&template.name) > 0.0
+    }
+}
+
+/// Where a template sits, as an index into `base_gestures` or
+/// `user_gestures`. Adding or removing templates can change it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemplateId {
+    Base(usize),
+    User(usize),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RecognizeStats {
+    pub templates: usize,
+    /// Templates with at least one start index that survived the lower bound.
+    pub templates_evaluated: usize,
+    pub counters: MatchCounters,
+    pub normalization_time: Duration,
+    pub matching_time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TemplateStats {
+    /// Times the template was the best match for a sample of its own class.
+    pub hits: usize,
+    /// Times the template was the best match for a sample of another class.
+    pub confusions: usize,
+}
+
+impl TemplateStats {
+    fn usefulness(&self) -> isize {
+        self.hits as isize - self.confusions as isize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LearnOutcome {
+    Unchanged,
+    Appended { index: usize },
+    Replaced { index: usize },
+    Evicted { index: usize },
+}
+
+impl Default for QDollarRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl QDollarRecognizer {
     pub fn new() -> Self {
+        Self::with_config(QDollarConfig::default())
+    }
+
+    pub fn with_config(config: QDollarConfig) -> Self {
         QDollarRecognizer {
+            distance: Arc::new(config.distance()),
+            config,
+            base_clouds: Arc::new(Vec::new()),
+            base_index: Arc::new(VpTree::default()),
             point_clouds: Vec::new(),
+            stats: Vec::new(),
+            clock: clock::default_clock(),
         }
     }
 
+    pub fn config(&self) -> &QDollarConfig {
+        &self.config
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Compares candidates with templates using `distance` instead of the
+    /// one `QDollarConfig::matching` selects.
+    pub fn set_distance(&mut self, distance: Arc<dyn CloudDistance>) {
+        self.distance = distance;
+    }
+
     pub fn recognize(&self, points: &[Point]) -> Result<QDollarResult, QDollarError> {
-        let now = std::time::Instant::now();
+        let mut results = self.recognize_with(points, &RecognizeOptions::default())?;
+        Ok(results.remove(0))
+    }
+
+    // Best `n` matches with at most one result per gesture name, best first.
+    pub fn recognize_n_best(
+        &self,
+        points: &[Point],
+        n: usize,
+    ) -> Result<Vec<QDollarResult>, QDollarError> {
+        let options = RecognizeOptions {
+            n,
+            ..RecognizeOptions::default()
+        };
+        self.recognize_with(points, &options)
+    }
+
+    /// Like `recognize`, choosing only among templates `vocabulary` allows.
+    pub fn recognize_in(
+        &self,
+        points: &[Point],
+        vocabulary: &Vocabulary,
+    ) -> Result<QDollarResult, QDollarError> {
+        let mut results = self.recognize_n_best_in(points, 1, vocabulary)?;
+        Ok(results.remove(0))
+    }
 
-        if self.point_clouds.is_empty() {
+    pub fn recognize_n_best_in(
+        &self,
+        points: &[Point],
+        n: usize,
+        vocabulary: &Vocabulary,
+    ) -> Result<Vec<QDollarResult>, QDollarError> {
+        let allows = |template: &PointCloud| vocabulary.allows(template);
+        let options = RecognizeOptions {
+            filter: TemplateFilter::Allow(&allows),
+            n,
+            ..RecognizeOptions::default()
+        };
+        self.recognize_with(points, &options)
+    }
+
+    /// Best `options.n` matches among the templates `options` leaves in,
+    /// weighted by its priors, best first.
+    pub fn recognize_with(
+        &self,
+        points: &[Point],
+        options: &RecognizeOptions,
+    ) -> Result<Vec<QDollarResult>, QDollarError> {
+        let n = options.n;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("recognize", points = points.len()).entered();
+        let start = self.clock.now();
+
+        let candidate = {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("normalize").entered();
+            PointCloud::try_with_config(String::new(), points.to_vec(), &self.config)?
+        };
+        let normalization_time = self.clock.now().saturating_sub(start);
+
+        let mut stats = RecognizeStats::default();
+        let mut matches = {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("match").entered();
+            self.best_matches(&candidate, n.max(1), options, &mut stats)
+        };
+        if matches.is_empty() {
             return Err(QDollarError::NoRegisteredGestures);
         }
-    
-        let candidate = PointCloud::new(String::new(), points.to_vec());
+        let time = self.clock.now().saturating_sub(start);
 
-        let mut best_distance = f64::INFINITY;
-        let mut best_template = 0;
+        stats.normalization_time = normalization_time;
+        stats.matching_time = time.saturating_sub(normalization_time);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            name = %matches[0].0.name,
+            distance = matches[0].1,
+            templates = stats.templates,
+            templates_evaluated = stats.templates_evaluated,
+            distance_calls = stats.counters.distance_calls,
+            starts_skipped = stats.counters.starts_skipped,
+            abandoned = stats.counters.abandoned,
+            normalization_us = stats.normalization_time.as_micros() as u64,
+            matching_us = stats.matching_time.as_micros() as u64,
+            "recognized gesture"
+        );
+        matches.truncate(n);
 
-        for (i, template) in self.point_clouds.iter().enumerate() {
-            let dist = utils::cloud_match(&candidate, template, best_distance);
+        Ok(matches
+            .into_iter()
+            .map(|(template, distance)| QDollarResult {
+                name: template.name.clone(),
+                score: score(distance),
+                template: self.template_id(template),
+                metadata: template.metadata.clone(),
+                time,
+                stats: self.config.collect_stats.then_some(stats),
+            })
+            .collect())
+    }
+
+    pub fn explain(&self, points: &[Point]) -> Result<Explanation, QDollarError> {
+        let candidate = PointCloud::try_with_config(String::new(), points.to_vec(), &self.config)?;
+
+        let all: Vec<&PointCloud> = self.templates().collect();
+        let order: Vec<usize> = match self.config.prefilter {
+            Prefilter::All => (0..all.len()).collect(),
+            Prefilter::Nearest { k } => self
+                .nearest_templates(&candidate, k, &|_| true)
+                .into_iter()
+                .filter_map(|template| all.iter().position(|t| core::ptr::eq(*t, template)))
+                .collect(),
+        };
+
+        let mut best_distance = f64::INFINITY;
+        let mut best = None;
+        let mut templates: Vec<TemplateEvaluation> = all
+            .iter()
+            .map(|template| TemplateEvaluation {
+                name: template.name.clone(),
+                status: TemplateStatus::Filtered,
+                distance: None,
+                counters: MatchCounters::default(),
+            })
+            .collect();
+        for i in order {
+            let template = all[i];
+            let mut counters = MatchCounters::default();
+            let dist = self.distance.distance(&candidate, template, best_distance, &mut counters);
+            let status = if counters.distance_calls == 0 {
+                TemplateStatus::Pruned
+            } else if dist < best_distance {
+                TemplateStatus::Evaluated
+            } else {
+                TemplateStatus::Abandoned
+            };
             if dist < best_distance {
                 best_distance = dist;
-                best_template = i;
+                best = Some((i, template));
+            }
+            templates[i].status = status;
+            templates[i].distance = (status != TemplateStatus::Pruned).then_some(dist);
+            templates[i].counters = counters;
+        }
+
+        let (best_template, template) = best.ok_or(QDollarError::NoRegisteredGestures)?;
+        Ok(Explanation {
+            name: template.name.clone(),
+            score: score(best_distance),
+            best_template,
+            alignment: explain::explain_match(&candidate, template),
+            templates,
+        })
+    }
+
+    pub fn learn(
+        &mut self,
+        points: &[Point],
+        correct_name: &str,
+    ) -> Result<LearnOutcome, QDollarError> {
+        let candidate =
+            PointCloud::try_with_config(correct_name.to_string(), points.to_vec(), &self.config)?;
+
+        let mut recognized = false;
+        let mut winner = None;
+        if let Some((template, _)) = self.best_match(&candidate, &mut RecognizeStats::default()) {
+            recognized = template.name == correct_name;
+            winner = self.user_index(template);
+            if let Some(index) = winner {
+                if recognized {
+                    self.stats[index].hits += 1;
+                } else {
+                    self.stats[index].confusions += 1;
+                }
             }
         }
 
-        let score = if best_distance > 1.0 {
-            1.0 / best_distance
-        } else {
-            1.0
+        let outcome = match self.config.learning_policy {
+            LearningPolicy::Append => self.push_learned(candidate),
+            LearningPolicy::ReplaceMostConfused => {
+                if recognized {
+                    return Ok(LearnOutcome::Unchanged);
+                }
+                // Keep the winner when it is the last template of its class,
+                // so learning never deletes a class.
+                let replaceable = winner.filter(|&index| {
+                    self.class_indices(&self.point_clouds[index].name).count() > 1
+                });
+                match replaceable {
+                    Some(index) => {
+                        self.point_clouds[index] = candidate;
+                        self.stats[index] = TemplateStats::default();
+                        LearnOutcome::Replaced { index }
+                    }
+                    None => self.push_learned(candidate),
+                }
+            }
+            LearningPolicy::CapPerClass { max_templates } => {
+                let outcome = self.push_learned(candidate);
+                let learned = self.point_clouds.len() - 1;
+                if self.class_indices(correct_name).count() <= max_templates {
+                    return Ok(outcome);
+                }
+                // Never evict the sample we were just taught.
+                let least_useful = self
+                    .class_indices(correct_name)
+                    .filter(|&i| i != learned)
+                    .min_by_key(|&i| self.stats[i].usefulness());
+                match least_useful {
+                    Some(index) => {
+                        self.point_clouds.remove(index);
+                        self.stats.remove(index);
+                        LearnOutcome::Evicted { index }
+                    }
+                    None => outcome,
+                }
+            }
         };
+        Ok(outcome)
+    }
 
-        Ok(QDollarResult {
-            name: self.point_clouds[best_template].name.clone(),
-            score,
-            time: now.elapsed(),
-        })
+    /// Adds a user template, returning the new template count. Fails with
+    /// `QDollarError::EmptyGesture` if `points` is empty.
+    pub fn add_gesture(
+        &mut self,
+        name: String,
+        points: Vec<Point>,
+    ) -> Result<usize, QDollarError> {
+        self.add_gesture_with_metadata(name, points, TemplateMetadata::default())
     }
 
-    pub fn add_gesture(&mut self, name: String, points: Vec<Point>) -> usize {
-        self.point_clouds.push(PointCloud::new(name, points));
-        self.point_clouds.len()
+    pub fn add_gesture_with_metadata(
+        &mut self,
+        name: String,
+        points: Vec<Point>,
+        metadata: TemplateMetadata,
+    ) -> Result<usize, QDollarError> {
+        let template = PointCloud::try_with_config(name, points, &self.config)?;
+        self.point_clouds.push(template.with_metadata(metadata));
+        self.stats.push(TemplateStats::default());
+        Ok(self.point_clouds.len())
+    }
+
+    /// Adds `count` synthetic variants of `points` as user gestures, tagged
+    /// `synth::SYNTHETIC_TAG`.
+    pub fn add_synthetic_gestures(
+        &mut self,
+        name: String,
+        points: &[Point],
+        count: usize,
+        synthesizer: &Synthesizer,
+    ) -> Result<usize, QDollarError> {
+        for variant in synthesizer.variants(points, count) {
+            let metadata = TemplateMetadata {
+                tags: vec![synth::SYNTHETIC_TAG.to_string()],
+                ..TemplateMetadata::default()
+            };
+            self.add_gesture_with_metadata(name.clone(), variant, metadata)?;
+        }
+        Ok(self.point_clouds.len())
     }
 
     pub fn delete_user_gestures(&mut self) -> usize {
         self.point_clouds.clear();
+        self.stats.clear();
+        self.point_clouds.len()
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), QDollarError> {
+        storage::save(path, &self.point_clouds)
+    }
+
+    // Replaces the user gestures with the ones stored at `path`.
+    #[cfg(feature = "std")]
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, QDollarError> {
+        let clouds = storage::load(path)?;
+        Ok(self.replace_user_gestures(clouds))
+    }
+
+    pub fn to_json(&self) -> Result<String, QDollarError> {
+        storage::to_json(&self.point_clouds)
+    }
+
+    pub fn load_json(&mut self, json: &str) -> Result<usize, QDollarError> {
+        let clouds = storage::from_json(json)?;
+        Ok(self.replace_user_gestures(clouds))
+    }
+
+    pub fn replace_user_gestures(&mut self, point_clouds: Vec<PointCloud>) -> usize {
+        self.stats = vec![TemplateStats::default(); point_clouds.len()];
+        self.point_clouds = point_clouds;
         self.point_clouds.len()
     }
+
+    pub fn user_gestures(&self) -> &[PointCloud] {
+        &self.point_clouds
+    }
+
+    pub fn user_gesture_stats(&self) -> &[TemplateStats] {
+        &self.stats
+    }
+
+    pub fn base_gestures(&self) -> &Arc<Vec<PointCloud>> {
+        &self.base_clouds
+    }
+
+    pub fn set_base_gestures(&mut self, base_clouds: Arc<Vec<PointCloud>>) {
+        self.base_index = Arc::new(VpTree::from_clouds(&base_clouds));
+        self.base_clouds = base_clouds;
+    }
+
+    /// A `CompiledRecognizer` snapshot of the templates recognition currently
+    /// considers. A distance set with `set_distance` is not carried over.
+    pub fn compile(&self) -> Result<CompiledRecognizer, QDollarError> {
+        let mut builder = CompiledRecognizerBuilder::new(self.config.clone());
+        builder.set_clock(self.clock.clone());
+        for template in self.templates() {
+            builder.add_template(template.clone());
+        }
+        builder.build()
+    }
+
+    fn templates(&self) -> impl Iterator<Item = &PointCloud> {
+        self.base_clouds
+            .iter()
+            .filter(move |cloud| !self.is_shadowed(cloud))
+            .chain(self.point_clouds.iter())
+    }
+
+    fn is_shadowed(&self, base_cloud: &PointCloud) -> bool {
+        self.config.user_shadows_base
+            && self.point_clouds.iter().any(|user| user.name == base_cloud.name)
+    }
+
+    // The `k` templates `allowed` accepts nearest the candidate in feature
+    // space, nearest first.
+    fn nearest_templates(
+        &self,
+        candidate: &PointCloud,
+        k: usize,
+        allowed: &dyn Fn(&PointCloud) -> bool,
+    ) -> Vec<&PointCloud> {
+        let query = candidate.features();
+        let mut nearest: Vec<(&PointCloud, f64)> = self
+            .base_index
+            .nearest(query, k, |i| {
+                let cloud = &self.base_clouds[i];
+                !self.is_shadowed(cloud) && allowed(cloud)
+            })
+            .into_iter()
+            .map(|(i, dist)| (&self.base_clouds[i], dist))
+            .collect();
+        nearest.extend(
+            self.point_clouds
+                .iter()
+                .filter(|cloud| allowed(cloud))
+                .map(|cloud| (cloud, cloud.features().distance(query))),
+        );
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+        nearest.truncate(k);
+        nearest.into_iter().map(|(cloud, _)| cloud).collect()
+    }
+
+    fn best_match(
+        &self,
+        candidate: &PointCloud,
+        stats: &mut RecognizeStats,
+    ) -> Option<(&PointCloud, f64)> {
+        self.best_matches(candidate, 1, &RecognizeOptions::default(), stats).pop()
+    }
+
+    // Keeps the closest template of each of the `n` closest gesture names
+    // among those `options` accepts, sorted by distance over prior. Matching
+    // abandons once it exceeds the n-th best.
+    fn best_matches(
+        &self,
+        candidate: &PointCloud,
+        n: usize,
+        options: &RecognizeOptions,
+        stats: &mut RecognizeStats,
+    ) -> Vec<(&PointCloud, f64)> {
+        let mut best: Vec<(&PointCloud, f64)> = Vec::with_capacity(n + 1);
+        if n == 0 {
+            return best;
+        }
+
+        let templates: Vec<&PointCloud> = match self.config.prefilter {
+            Prefilter::All => self.templates().filter(|t| options.accepts(t)).collect(),
+            Prefilter::Nearest { k } => {
+                self.nearest_templates(candidate, k, &|t| options.accepts(t))
+            }
+        };
+        for template in templates {
+            let prior = options.prior(&template.name);
+            let threshold = if best.len() == n {
+                best[n - 1].1 * prior
+            } else {
+                f64::INFINITY
+            };
+            let calls = stats.counters.distance_calls;
+            let dist = self.distance.distance(candidate, template, threshold, &mut stats.counters);
+            stats.templates += 1;
+            if stats.counters.distance_calls > calls {
+                stats.templates_evaluated += 1;
+            }
+            if dist >= threshold {
+                continue;
+            }
+            let dist = dist / prior;
+            if let Some(i) = best.iter().position(|(t, _)| t.name == template.name) {
+                if dist >= best[i].1 {
+                    continue;
+                }
+                best.remove(i);
+            }
+            let at = best.partition_point(|&(_, d)| d <= dist);
+            best.insert(at, (template, dist));
+            best.truncate(n);
+        }
+
+        best
+    }
+
+    fn template_id(&self, template: &PointCloud) -> TemplateId {
+        match self.user_index(template) {
+            Some(index) => TemplateId::User(index),
+            None => TemplateId::Base(
+                self.base_clouds
+                    .iter()
+                    .position(|cloud| core::ptr::eq(cloud, template))
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    fn user_index(&self, template: &PointCloud) -> Option<usize> {
+        self.point_clouds
+            .iter()
+            .position(|cloud| core::ptr::eq(cloud, template))
+    }
+
+    fn class_indices<'a>(&'a self, name: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.point_clouds
+            .iter()
+            .enumerate()
+            .filter(move |(_, cloud)| cloud.name == name)
+            .map(|(i, _)| i)
+    }
+
+    fn push_learned(&mut self, candidate: PointCloud) -> LearnOutcome {
+        self.point_clouds.push(candidate);
+        self.stats.push(TemplateStats::default());
+        LearnOutcome::Appended {
+            index: self.point_clouds.len() - 1,
+        }
+    }
+}
+
+pub(crate) fn score(distance: f64) -> f64 {
+    if distance > 1.0 {
+        1.0 / distance
+    } else {
+        1.0
+    }
 }
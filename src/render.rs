@@ -0,0 +1,212 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use resvg::{tiny_skia, usvg};
+
+use crate::error::QDollarError;
+use crate::{explain, utils, Point, PointCloud, QDollarConfig};
+
+/// Options for both SVG and PNG output. `size` is the side of the square
+/// image, in pixels for PNG.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgOptions {
+    pub size: u32,
+    pub show_lut: bool,
+    pub point_radius: f64,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            size: 512,
+            show_lut: true,
+            point_radius: 4.0,
+        }
+    }
+}
+
+const CANDIDATE_COLOUR: &str = "#1f77b4";
+const TEMPLATE_COLOUR: &str = "#d62728";
+const RAW_COLOUR: &str = "#7f7f7f";
+
+// Resampled points and, optionally, the nearest-point LUT of a cloud.
+pub fn cloud_svg(cloud: &PointCloud, options: &SvgOptions) -> String {
+    let mut svg = header(options);
+    if options.show_lut {
+        draw_lut(&mut svg, cloud, options);
+    }
+    draw_points(&mut svg, &cloud.points, CANDIDATE_COLOUR, options);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// A raw gesture overlaid on the cloud it normalizes to under `config`.
+pub fn gesture_svg(points: &[Point], config: &QDollarConfig, options: &SvgOptions) -> String {
+    let cloud = PointCloud::with_config(String::new(), points.to_vec(), config);
+    let mut svg = header(options);
+    if options.show_lut {
+        draw_lut(&mut svg, &cloud, options);
+    }
+
+    let resampled = config
+        .resample_strategy
+        .resample(&config.preprocess(points), utils::NUM_POINTS);
+    let raw = normalize_like(points, &resampled);
+    for stroke in raw.chunk_by(|a, b| a.id == b.id) {
+        let coords: Vec<String> = stroke
+            .iter()
+            .map(|pt| {
+                let (x, y) = to_canvas(pt, options);
+                format!("{:.2},{:.2}", x, y)
+            })
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+            coords.join(" "),
+            RAW_COLOUR
+        );
+    }
+
+    draw_points(&mut svg, &cloud.points, CANDIDATE_COLOUR, options);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Both clouds with a line for every pair in the matching `cloud_match` chose.
+pub fn alignment_svg(
+    candidate: &PointCloud,
+    template: &PointCloud,
+    options: &SvgOptions,
+) -> String {
+    let mut svg = header(options);
+    for pair in explain::explain_match(candidate, template).pairs {
+        let (x1, y1) = to_canvas(&candidate.points[pair.candidate_index], options);
+        let (x2, y2) = to_canvas(&template.points[pair.template_index], options);
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="#2ca02c" stroke-width="1"/>"##,
+            x1, y1, x2, y2
+        );
+    }
+    draw_points(&mut svg, &template.points, TEMPLATE_COLOUR, options);
+    draw_points(&mut svg, &candidate.points, CANDIDATE_COLOUR, options);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+pub fn cloud_png(cloud: &PointCloud, options: &SvgOptions) -> Result<Vec<u8>, QDollarError> {
+    svg_to_png(&cloud_svg(cloud, options))
+}
+
+pub fn gesture_png(
+    points: &[Point],
+    config: &QDollarConfig,
+    options: &SvgOptions,
+) -> Result<Vec<u8>, QDollarError> {
+    svg_to_png(&gesture_svg(points, config, options))
+}
+
+pub fn alignment_png(
+    candidate: &PointCloud,
+    template: &PointCloud,
+    options: &SvgOptions,
+) -> Result<Vec<u8>, QDollarError> {
+    svg_to_png(&alignment_svg(candidate, template, options))
+}
+
+// Rasterizes an SVG, such as one of the above, to PNG at the SVG's own size.
+pub fn svg_to_png(svg: &str) -> Result<Vec<u8>, QDollarError> {
+    let tree = usvg::Tree::from_str(svg, &usvg::Options::default())
+        .map_err(|e| QDollarError::Serialization(e.to_string()))?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or_else(|| {
+        QDollarError::Serialization(format!(
+            "cannot rasterize a {}x{} image",
+            size.width(),
+            size.height()
+        ))
+    })?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|e| QDollarError::Serialization(e.to_string()))
+}
+
+fn header(options: &SvgOptions) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n\
+         <rect width=\"{0}\" height=\"{0}\" fill=\"white\"/>\n",
+        options.size
+    )
+}
+
+fn draw_points(svg: &mut String, points: &[Point], colour: &str, options: &SvgOptions) {
+    for pt in points {
+        let (x, y) = to_canvas(pt, options);
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{}" fill="{}"><title>{}</title></circle>"#,
+            x, y, options.point_radius, colour, pt.id
+        );
+    }
+}
+
+fn draw_lut(svg: &mut String, cloud: &PointCloud, options: &SvgOptions) {
+    let n = cloud.points.len().max(1);
+    let cell = options.size as f64 / utils::LUT_SIZE as f64;
+    for (x, row) in cloud.lut.iter().enumerate() {
+        for (y, &index) in row.iter().enumerate() {
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="hsl({}, 60%, 88%)"/>"#,
+                x as f64 * cell,
+                y as f64 * cell,
+                cell,
+                cell,
+                index * 360 / n
+            );
+        }
+    }
+}
+
+// Normalized coordinates span roughly [-1, 1], like the integer grid.
+fn to_canvas(pt: &Point, options: &SvgOptions) -> (f64, f64) {
+    let size = options.size as f64;
+    ((pt.x + 1.0) / 2.0 * size, (pt.y + 1.0) / 2.0 * size)
+}
+
+// Applies the scale and translation that turned `resampled` into a cloud.
+fn normalize_like(points: &[Point], resampled: &[Point]) -> Vec<Point> {
+    let (min_x, max_x, min_y, max_y) = resampled.iter().fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(min_x, max_x, min_y, max_y), pt| {
+            (
+                min_x.min(pt.x),
+                max_x.max(pt.x),
+                min_y.min(pt.y),
+                max_y.max(pt.y),
+            )
+        },
+    );
+    let size = (max_x - min_x).max(max_y - min_y) + 1e-6;
+    let centroid = utils::centroid(&utils::scale(resampled));
+
+    points
+        .iter()
+        .map(|pt| {
+            Point::new(
+                (pt.x - min_x) / size - centroid.x,
+                (pt.y - min_y) / size - centroid.y,
+                pt.id,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,186 @@
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Sub};
+
+use crate::utils::MAX_INT_COORD;
+use crate::{Point, PointCloud};
+
+/// Number type the matcher computes squared distances in.
+///
+/// `f64` and `f32` use the normalized coordinates. `i64` works purely on the
+/// `int_x`/`int_y` grid, so its distances are in squared grid units.
+pub trait Scalar:
+    Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+    const ZERO: Self;
+    /// Added to the distance of matched points so `nearest` skips them.
+    const MATCHED: Self;
+
+    fn coords(pt: &Point) -> [Self; 2];
+
+    /// The cloud's coordinates in `Self`, stored when the cloud was built.
+    fn cloud_coords(cloud: &PointCloud) -> &Coords<Self>;
+
+    fn from_usize(n: usize) -> Self;
+
+    /// Converts a distance in normalized units.
+    fn from_distance(distance: f64) -> Self;
+
+    /// Converts back to the normalized units used by `f64`.
+    fn to_distance(self) -> f64;
+
+    /// Index of the point of `coords` nearest to `pt` once `penalty` is added
+    /// to each squared distance, and that distance. Ties go to the lowest index.
+    fn nearest(pt: [Self; 2], coords: &Coords<Self>, penalty: &[Self]) -> (usize, Self) {
+        nearest_scalar(pt, coords, penalty)
+    }
+}
+
+/// Structure-of-arrays copy of a cloud's coordinates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coords<S> {
+    pub xs: Vec<S>,
+    pub ys: Vec<S>,
+}
+
+impl<S: Scalar> Coords<S> {
+    pub fn new(points: &[Point]) -> Self {
+        let (xs, ys) = points
+            .iter()
+            .map(|pt| {
+                let [x, y] = S::coords(pt);
+                (x, y)
+            })
+            .unzip();
+        Coords { xs, ys }
+    }
+
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> [S; 2] {
+        [self.xs[i], self.ys[i]]
+    }
+}
+
+pub fn sqr_distance<S: Scalar>(pt1: [S; 2], pt2: [S; 2]) -> S {
+    let dx = pt2[0] - pt1[0];
+    let dy = pt2[1] - pt1[1];
+    dx * dx + dy * dy
+}
+
+/// One point at a time. The reference the SIMD versions must agree with.
+pub fn nearest_scalar<S: Scalar>(pt: [S; 2], coords: &Coords<S>, penalty: &[S]) -> (usize, S) {
+    let mut min_dist = sqr_distance(pt, coords.get(0)) + penalty[0];
+    let mut min_index = 0;
+    for (j, &pen) in penalty.iter().enumerate().take(coords.len()).skip(1) {
+        let d = sqr_distance(pt, coords.get(j)) + pen;
+        if d < min_dist {
+            min_dist = d;
+            min_index = j;
+        }
+    }
+    (min_index, min_dist)
+}
+
+impl Scalar for f64 {
+    const ZERO: Self = 0.0;
+    const MATCHED: Self = f64::INFINITY;
+
+    fn coords(pt: &Point) -> [Self; 2] {
+        [pt.x, pt.y]
+    }
+
+    fn cloud_coords(cloud: &PointCloud) -> &Coords<Self> {
+        cloud.coords()
+    }
+
+    fn from_usize(n: usize) -> Self {
+        n as f64
+    }
+
+    fn from_distance(distance: f64) -> Self {
+        distance
+    }
+
+    fn to_distance(self) -> f64 {
+        self
+    }
+
+    #[cfg(feature = "simd")]
+    fn nearest(pt: [Self; 2], coords: &Coords<Self>, penalty: &[Self]) -> (usize, Self) {
+        crate::simd::nearest_f64(pt, coords, penalty)
+    }
+}
+
+impl Scalar for f32 {
+    const ZERO: Self = 0.0;
+    const MATCHED: Self = f32::INFINITY;
+
+    fn coords(pt: &Point) -> [Self; 2] {
+        [pt.x as f32, pt.y as f32]
+    }
+
+    fn cloud_coords(cloud: &PointCloud) -> &Coords<Self> {
+        cloud.coords_f32()
+    }
+
+    fn from_usize(n: usize) -> Self {
+        n as f32
+    }
+
+    fn from_distance(distance: f64) -> Self {
+        distance as f32
+    }
+
+    fn to_distance(self) -> f64 {
+        self as f64
+    }
+
+    #[cfg(feature = "simd")]
+    fn nearest(pt: [Self; 2], coords: &Coords<Self>, penalty: &[Self]) -> (usize, Self) {
+        crate::simd::nearest_f32(pt, coords, penalty)
+    }
+}
+
+// Squared size of one grid step in normalized units.
+const GRID_UNIT_SQR: f64 = (2.0 / (MAX_INT_COORD - 1) as f64) * (2.0 / (MAX_INT_COORD - 1) as f64);
+
+impl Scalar for i64 {
+    const ZERO: Self = 0;
+    // Far above any squared distance on the grid, and safe to add to one.
+    const MATCHED: Self = i64::MAX / 2;
+
+    fn coords(pt: &Point) -> [Self; 2] {
+        [pt.int_x as i64, pt.int_y as i64]
+    }
+
+    fn cloud_coords(cloud: &PointCloud) -> &Coords<Self> {
+        cloud.coords_grid()
+    }
+
+    fn from_usize(n: usize) -> Self {
+        n as i64
+    }
+
+    fn from_distance(distance: f64) -> Self {
+        let units = libm::ceil(distance / GRID_UNIT_SQR);
+        if units >= i64::MAX as f64 {
+            i64::MAX
+        } else {
+            units as i64
+        }
+    }
+
+    fn to_distance(self) -> f64 {
+        if self == i64::MAX {
+            f64::INFINITY
+        } else {
+            self as f64 * GRID_UNIT_SQR
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use wide::{f32x8, f64x4, CmpLt};
+
+use crate::scalar::{nearest_scalar, Coords};
+
+// Each lane keeps the first strict minimum of the indices it sees, and lanes
+// see ascending indices, so picking the smallest (distance, index) across lanes
+// gives the same answer as `nearest_scalar`. Lane arithmetic is the same
+// subtract, multiply and add, so distances are bit-identical too.
+macro_rules! nearest_simd {
+    ($name:ident, $scalar:ty, $vector:ty, $lanes:expr) => {
+        pub fn $name(
+            pt: [$scalar; 2],
+            coords: &Coords<$scalar>,
+            penalty: &[$scalar],
+        ) -> (usize, $scalar) {
+            let chunks = coords.len() / $lanes;
+            if chunks == 0 {
+                return nearest_scalar(pt, coords, penalty);
+            }
+
+            let px = <$vector>::splat(pt[0]);
+            let py = <$vector>::splat(pt[1]);
+            let mut best = <$vector>::splat(<$scalar>::INFINITY);
+            let mut best_index = <$vector>::splat(0.0);
+            let mut index = <$vector>::from(core::array::from_fn::<$scalar, $lanes, _>(|lane| {
+                lane as $scalar
+            }));
+            let step = <$vector>::splat($lanes as $scalar);
+
+            for ((xs, ys), pen) in coords
+                .xs
+                .chunks_exact($lanes)
+                .zip(coords.ys.chunks_exact($lanes))
+                .zip(penalty.chunks_exact($lanes))
+            {
+                let dx = <$vector>::from(<[$scalar; $lanes]>::try_from(xs).unwrap()) - px;
+                let dy = <$vector>::from(<[$scalar; $lanes]>::try_from(ys).unwrap()) - py;
+                let d = dx * dx
+                    + dy * dy
+                    + <$vector>::from(<[$scalar; $lanes]>::try_from(pen).unwrap());
+                let closer = d.cmp_lt(best);
+                best = closer.blend(d, best);
+                best_index = closer.blend(index, best_index);
+                index += step;
+            }
+
+            let mut min_dist = <$scalar>::INFINITY;
+            let mut min_index = usize::MAX;
+            for (d, i) in best.to_array().into_iter().zip(best_index.to_array()) {
+                let i = i as usize;
+                if d < min_dist || (d == min_dist && i < min_index) {
+                    min_dist = d;
+                    min_index = i;
+                }
+            }
+            for j in chunks * $lanes..coords.len() {
+                let d = crate::scalar::sqr_distance(pt, coords.get(j)) + penalty[j];
+                if d < min_dist {
+                    min_dist = d;
+                    min_index = j;
+                }
+            }
+
+            // Only non-finite distances never beat infinity.
+            if min_index == usize::MAX {
+                return nearest_scalar(pt, coords, penalty);
+            }
+            (min_index, min_dist)
+        }
+    };
+}
+
+nearest_simd!(nearest_f64, f64, f64x4, 4);
+nearest_simd!(nearest_f32, f32, f32x8, 8);
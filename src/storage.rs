@@ -0,0 +1,103 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::QDollarError;
+use crate::metadata::TemplateMetadata;
+use crate::{utils, Point, PointCloud};
+
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct StoredTemplate {
+    name: String,
+    points: Vec<Point>,
+    #[serde(default, skip_serializing_if = "TemplateMetadata::is_empty")]
+    metadata: TemplateMetadata,
+    // Missing from older files, whose templates fall back to the strokes left
+    // after resampling.
+    #[serde(default)]
+    stroke_count: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TemplateFile {
+    version: u32,
+    templates: Vec<StoredTemplate>,
+}
+
+// Templates are stored already normalized, so loading skips resampling and
+// only rebuilds the integer coordinates and LUTs.
+pub fn to_json(clouds: &[PointCloud]) -> Result<String, QDollarError> {
+    let file = TemplateFile {
+        version: FORMAT_VERSION,
+        templates: clouds
+            .iter()
+            .map(|cloud| StoredTemplate {
+                name: cloud.name.clone(),
+                points: cloud.points.clone(),
+                metadata: cloud.metadata.clone(),
+                stroke_count: Some(cloud.stroke_count()),
+            })
+            .collect(),
+    };
+    serde_json::to_string(&file).map_err(|e| QDollarError::Serialization(e.to_string()))
+}
+
+pub fn from_json(json: &str) -> Result<Vec<PointCloud>, QDollarError> {
+    let file: TemplateFile =
+        serde_json::from_str(json).map_err(|e| QDollarError::Serialization(e.to_string()))?;
+    if file.version != FORMAT_VERSION {
+        return Err(QDollarError::Serialization(format!(
+            "unsupported template format version {}",
+            file.version
+        )));
+    }
+
+    file.templates
+        .into_iter()
+        .map(|template| {
+            if template.points.len() != utils::NUM_POINTS {
+                return Err(QDollarError::InvalidTemplate(format!(
+                    "{} has {} points, expected {}",
+                    template.name,
+                    template.points.len(),
+                    utils::NUM_POINTS
+                )));
+            }
+            if template
+                .points
+                .iter()
+                .any(|pt| !pt.x.is_finite() || !pt.y.is_finite())
+            {
+                return Err(QDollarError::InvalidTemplate(format!(
+                    "{} has non-finite coordinates",
+                    template.name
+                )));
+            }
+            let cloud = PointCloud::from_normalized(template.name, template.points)
+                .with_metadata(template.metadata);
+            Ok(match template.stroke_count {
+                Some(stroke_count) => cloud.with_stroke_count(stroke_count),
+                None => cloud,
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+pub fn save<P: AsRef<Path>>(path: P, clouds: &[PointCloud]) -> Result<(), QDollarError> {
+    fs::write(path, to_json(clouds)?).map_err(|e| QDollarError::Io(e.to_string()))
+}
+
+#[cfg(feature = "std")]
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<PointCloud>, QDollarError> {
+    let json = fs::read_to_string(path).map_err(|e| QDollarError::Io(e.to_string()))?;
+    from_json(&json)
+}
@@ -0,0 +1,216 @@
+use alloc::vec::Vec;
+
+use crate::rng::{self, Rng, SplitMix64};
+use crate::{utils, Point};
+
+/// Tag of the templates `QDollarRecognizer::add_synthetic_gestures` adds.
+pub const SYNTHETIC_TAG: &str = "synthetic";
+
+/// Generates distorted copies of a gesture, to stand in for the samples a
+/// user never drew.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Synthesizer {
+    /// Largest rotation either way, in radians.
+    pub max_rotation: f64,
+    /// Largest relative change of the horizontal and vertical scale, drawn
+    /// separately for each axis.
+    pub max_scale: f64,
+    /// Largest offset added to each point, relative to the gesture size.
+    pub jitter: f64,
+    /// Draw the strokes of multistroke gestures in a random order.
+    pub permute_strokes: bool,
+    /// Reverse each stroke with probability one half.
+    pub reverse_strokes: bool,
+    /// Resample with gesture path stochastic resampling before the other
+    /// distortions.
+    pub gpsr: Option<Gpsr>,
+    pub seed: u64,
+}
+
+impl Default for Synthesizer {
+    fn default() -> Self {
+        Synthesizer {
+            max_rotation: 0.15,
+            max_scale: 0.1,
+            jitter: 0.01,
+            permute_strokes: true,
+            reverse_strokes: true,
+            gpsr: None,
+            seed: 0,
+        }
+    }
+}
+
+/// Gesture path stochastic resampling, after Taranta et al.: points are
+/// placed at random intervals along the path, and the gesture is rebuilt
+/// from unit steps in the directions between them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gpsr {
+    pub points: usize,
+    /// Variance of the interval lengths before normalization. The paper
+    /// recommends 0.25.
+    pub variance: f64,
+}
+
+impl Default for Gpsr {
+    fn default() -> Self {
+        Gpsr {
+            points: utils::NUM_POINTS,
+            variance: 0.25,
+        }
+    }
+}
+
+impl Synthesizer {
+    /// `count` variants of `points`, the same for the same seed.
+    pub fn variants(&self, points: &[Point], count: usize) -> Vec<Vec<Point>> {
+        self.variants_with(points, count, &mut SplitMix64::new(self.seed))
+    }
+
+    /// Like `variants`, drawing from `rng` and ignoring `seed`.
+    pub fn variants_with<R: Rng + ?Sized>(
+        &self,
+        points: &[Point],
+        count: usize,
+        rng: &mut R,
+    ) -> Vec<Vec<Point>> {
+        (0..count).map(|_| self.variant(points, rng)).collect()
+    }
+
+    fn variant<R: Rng + ?Sized>(&self, points: &[Point], rng: &mut R) -> Vec<Point> {
+        let mut strokes: Vec<Vec<Point>> = points
+            .chunk_by(|a, b| a.id == b.id)
+            .map(|stroke| stroke.to_vec())
+            .collect();
+        if self.permute_strokes {
+            rng::shuffle(&mut strokes, rng);
+        }
+        if self.reverse_strokes {
+            for stroke in &mut strokes {
+                if rng.next_f64() < 0.5 {
+                    stroke.reverse();
+                }
+            }
+        }
+        let mut points = strokes.concat();
+        if let Some(gpsr) = self.gpsr {
+            points = gpsr.resample(&points, rng);
+        }
+        if points.is_empty() {
+            return points;
+        }
+
+        let center = utils::centroid(&points);
+        let (sin, cos) = libm::sincos(rng.range(-self.max_rotation, self.max_rotation));
+        let sx = 1.0 + rng.range(-self.max_scale, self.max_scale);
+        let sy = 1.0 + rng.range(-self.max_scale, self.max_scale);
+        let (min_x, max_x, min_y, max_y) = bounds(&points);
+        let jitter = self.jitter * (max_x - min_x).max(max_y - min_y);
+        for pt in &mut points {
+            let x = (pt.x - center.x) * sx;
+            let y = (pt.y - center.y) * sy;
+            pt.x = center.x + x * cos - y * sin + rng.range(-jitter, jitter);
+            pt.y = center.y + x * sin + y * cos + rng.range(-jitter, jitter);
+        }
+        points
+    }
+}
+
+impl Gpsr {
+    fn resample<R: Rng + ?Sized>(&self, points: &[Point], rng: &mut R) -> Vec<Point> {
+        let length = utils::path_length(points);
+        if points.is_empty() || self.points < 2 || length <= 0.0 {
+            return points.to_vec();
+        }
+
+        let spread = libm::sqrt(12.0 * self.variance);
+        let intervals: Vec<f64> = (1..self.points)
+            .map(|_| 1.0 + rng.next_f64() * spread)
+            .collect();
+        let total: f64 = intervals.iter().sum();
+        let mut targets = Vec::with_capacity(self.points);
+        let mut at = 0.0;
+        targets.push(0.0);
+        for interval in &intervals {
+            at += interval / total * length;
+            targets.push(at.min(length));
+        }
+        let samples = points_at(points, &targets);
+
+        // Unit steps within a stroke. Jumps between strokes keep their
+        // direction and are scaled like the path, whose mean step is
+        // length / (points - 1).
+        let jump_scale = (self.points - 1) as f64 / length;
+        let mut rebuilt = Vec::with_capacity(samples.len());
+        rebuilt.push(samples[0]);
+        for pair in samples.windows(2) {
+            let prev = rebuilt[rebuilt.len() - 1];
+            let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+            let step = libm::sqrt(dx * dx + dy * dy);
+            let scale = if pair[0].id != pair[1].id {
+                jump_scale
+            } else if step > 0.0 {
+                1.0 / step
+            } else {
+                0.0
+            };
+            rebuilt.push(Point::new(
+                prev.x + dx * scale,
+                prev.y + dy * scale,
+                pair[1].id,
+            ));
+        }
+        rebuilt
+    }
+}
+
+// The points at each of the ascending distances `targets` along the path,
+// not counting the jumps between strokes.
+fn points_at(points: &[Point], targets: &[f64]) -> Vec<Point> {
+    let mut samples = Vec::with_capacity(targets.len());
+    let mut targets = targets.iter().peekable();
+    let mut walked = 0.0;
+    for pair in points.windows(2).filter(|pair| pair[0].id == pair[1].id) {
+        let segment = utils::euclidean_distance(&pair[0], &pair[1]);
+        while let Some(&&target) = targets.peek() {
+            if target > walked + segment {
+                break;
+            }
+            let ratio = if segment > 0.0 {
+                (target - walked) / segment
+            } else {
+                0.0
+            };
+            samples.push(Point::new(
+                pair[0].x + ratio * (pair[1].x - pair[0].x),
+                pair[0].y + ratio * (pair[1].y - pair[0].y),
+                pair[0].id,
+            ));
+            targets.next();
+        }
+        walked += segment;
+    }
+    // Rounding can leave the last targets just past the end.
+    let last = points[points.len() - 1];
+    samples.extend(targets.map(|_| Point::new(last.x, last.y, last.id)));
+    samples
+}
+
+fn bounds(points: &[Point]) -> (f64, f64, f64, f64) {
+    points.iter().fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(min_x, max_x, min_y, max_y), pt| {
+            (
+                min_x.min(pt.x),
+                max_x.max(pt.x),
+                min_y.min(pt.y),
+                max_y.max(pt.y),
+            )
+        },
+    )
+}
@@ -1,3 +1,9 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::assignment;
+use crate::config::StrokeFeatures;
+use crate::scalar::{sqr_distance, Coords, Scalar};
 use crate::{Point, PointCloud};
 
 pub const NUM_POINTS: usize = 32;
@@ -5,6 +11,7 @@ pub const ORIGIN: Point = Point {
     x: 0.0,
     y: 0.0,
     id: 0,
+    time: 0.0,
     int_x: 0,
     int_y: 0,
 };
@@ -12,36 +19,173 @@ pub const MAX_INT_COORD: usize = 1024;
 pub const LUT_SIZE: usize = 64;
 pub const LUT_SCALE_FACTOR: f64 = MAX_INT_COORD as f64 / LUT_SIZE as f64;
 
+// Exactly `n` points for any non-empty input. There is nothing to resample in
+// an empty gesture, so it gives no points; `PointCloud::try_with_config`
+// rejects those before they become a cloud.
 pub fn resample(points: &[Point], n: usize) -> Vec<Point> {
-    let path_length = path_length(points);
-    let interval = path_length / (n - 1) as f64;
-    let mut new_points = vec![points[0]];
+    resample_along(points, n, euclidean_distance)
+}
+
+pub fn resample_by_time(points: &[Point], n: usize) -> Vec<Point> {
+    let duration: f64 = points
+        .windows(2)
+        .filter(|pair| pair[0].id == pair[1].id)
+        .map(|pair| elapsed(&pair[0], &pair[1]))
+        .sum();
+    if duration <= 0.0 {
+        // No usable timestamps, fall back to the path.
+        return resample(points, n);
+    }
+    resample_along(points, n, elapsed)
+}
+
+pub fn resample_per_stroke(points: &[Point], n: usize) -> Vec<Point> {
+    let strokes: Vec<&[Point]> = points.chunk_by(|a, b| a.id == b.id).collect();
+    if strokes.len() <= 1 || strokes.len() > n {
+        return resample(points, n);
+    }
+
+    let lengths: Vec<f64> = strokes.iter().map(|stroke| path_length(stroke)).collect();
+    let counts = allocate(&lengths, n, 1);
+    strokes
+        .iter()
+        .zip(counts)
+        .flat_map(|(stroke, count)| resample(stroke, count))
+        .collect()
+}
+
+pub fn resample_curvature_adaptive(points: &[Point], n: usize, corner_angle: f64) -> Vec<Point> {
+    // Anchors are stroke endpoints and corners; they are always kept and the
+    // remaining points are spread over the spans between them.
+    let mut anchors: Vec<Point> = Vec::new();
+    let mut spans: Vec<(usize, Vec<Point>)> = Vec::new();
+    for stroke in points.chunk_by(|a, b| a.id == b.id) {
+        let mut vertices = stroke.to_vec();
+        vertices.dedup_by(|b, a| a.x == b.x && a.y == b.y);
+
+        anchors.push(vertices[0]);
+        let mut span_start = 0;
+        for i in 1..vertices.len() {
+            let is_end = i == vertices.len() - 1;
+            if is_end
+                || turning_angle(&vertices[i - 1], &vertices[i], &vertices[i + 1]) >= corner_angle
+            {
+                spans.push((anchors.len() - 1, vertices[span_start..=i].to_vec()));
+                anchors.push(vertices[i]);
+                span_start = i;
+            }
+        }
+    }
+    if anchors.is_empty() || anchors.len() > n {
+        return resample(points, n);
+    }
+
+    let lengths: Vec<f64> = spans.iter().map(|(_, span)| path_length(span)).collect();
+    let counts = allocate(&lengths, n - anchors.len(), 0);
+    let mut interior: Vec<Vec<Point>> = vec![Vec::new(); anchors.len()];
+    for ((anchor, span), count) in spans.iter().zip(counts) {
+        let resampled = resample(span, count + 2);
+        interior[*anchor] = resampled[1..=count].to_vec();
+    }
+
+    let last = points[points.len() - 1];
+    let mut new_points: Vec<Point> = anchors
+        .into_iter()
+        .zip(interior)
+        .flat_map(|(anchor, interior)| core::iter::once(anchor).chain(interior))
+        .collect();
+    new_points.resize(n, last);
+    new_points
+}
+
+fn resample_along(points: &[Point], n: usize, measure: fn(&Point, &Point) -> f64) -> Vec<Point> {
+    if points.is_empty() || n == 0 {
+        return Vec::new();
+    }
+
+    let first = points[0];
+    let last = points[points.len() - 1];
+    let total: f64 = points
+        .windows(2)
+        .filter(|pair| pair[0].id == pair[1].id)
+        .map(|pair| measure(&pair[0], &pair[1]))
+        .sum();
+    if total <= 0.0 {
+        return vec![first; n];
+    }
+
+    let interval = total / (n - 1) as f64;
+    let mut new_points = Vec::with_capacity(n);
+    new_points.push(first);
     let mut dist = 0.0;
-    let mut i = 1;
-    let mut points_vec = points.to_vec();
-    while i < points_vec.len() {
-        if points_vec[i].id == points_vec[i - 1].id {
-            let distance = euclidean_distance(&points_vec[i - 1], &points_vec[i]);
-            if dist + distance >= interval {
+    let mut prev = first;
+    for &pt in &points[1..] {
+        if pt.id == prev.id {
+            let mut distance = measure(&prev, &pt);
+            while distance > 0.0 && dist + distance >= interval && new_points.len() < n - 1 {
                 let ratio = (interval - dist) / distance;
-                let new_x = points_vec[i - 1].x + ratio * (points_vec[i].x - points_vec[i - 1].x);
-                let new_y = points_vec[i - 1].y + ratio * (points_vec[i].y - points_vec[i - 1].y);
-                let new_point = Point::new(new_x, new_y, points_vec[i].id);
-                new_points.push(new_point);
-                points_vec.insert(i, new_point);
+                let new_x = prev.x + ratio * (pt.x - prev.x);
+                let new_y = prev.y + ratio * (pt.y - prev.y);
+                let new_time = prev.time + ratio * (pt.time - prev.time);
+                prev = Point::with_time(new_x, new_y, pt.id, new_time);
+                new_points.push(prev);
+                distance = measure(&prev, &pt);
                 dist = 0.0;
-            } else {
-                dist += distance;
             }
+            dist += distance;
         }
-        i += 1;
+        prev = pt;
     }
-    if new_points.len() == n - 1 {
-        new_points.push(*points_vec.last().unwrap());
+    // Floating-point drift can leave us one interpolated point short; the
+    // path always ends on the original last point.
+    if new_points.len() < n {
+        new_points.push(last);
     }
+    new_points.resize(n, last);
     new_points
 }
 
+// Splits `n` points between parts proportionally to their lengths, with at
+// least `min_each` per part, using the largest remainder method.
+fn allocate(lengths: &[f64], n: usize, min_each: usize) -> Vec<usize> {
+    let mut counts = vec![min_each; lengths.len()];
+    let spare = n - min_each * lengths.len();
+    let total: f64 = lengths.iter().sum();
+    let shares: Vec<f64> = lengths
+        .iter()
+        .map(|&len| {
+            if total > 0.0 {
+                len / total * spare as f64
+            } else {
+                spare as f64 / lengths.len() as f64
+            }
+        })
+        .collect();
+
+    for (count, share) in counts.iter_mut().zip(&shares) {
+        *count += libm::floor(*share) as usize;
+    }
+    let mut order: Vec<usize> = (0..lengths.len()).collect();
+    order.sort_by(|&a, &b| {
+        (shares[b] - libm::floor(shares[b])).total_cmp(&(shares[a] - libm::floor(shares[a])))
+    });
+    let left = n - counts.iter().sum::<usize>();
+    for &i in order.iter().take(left) {
+        counts[i] += 1;
+    }
+    counts
+}
+
+pub(crate) fn turning_angle(prev: &Point, pt: &Point, next: &Point) -> f64 {
+    let (ax, ay) = (pt.x - prev.x, pt.y - prev.y);
+    let (bx, by) = (next.x - pt.x, next.y - pt.y);
+    libm::atan2(ax * by - ay * bx, ax * bx + ay * by).abs()
+}
+
+fn elapsed(pt1: &Point, pt2: &Point) -> f64 {
+    (pt2.time - pt1.time).abs()
+}
+
 pub fn scale(points: &[Point]) -> Vec<Point> {
     let (min_x, max_x, min_y, max_y) = points.iter().fold(
         (
@@ -81,24 +225,29 @@ pub fn make_int_coords(points: &[Point]) -> Vec<Point> {
         .iter()
         .map(|pt| {
             let mut p = *pt;
-            p.int_x = ((pt.x + 1.0) / 2.0 * (MAX_INT_COORD - 1) as f64).round() as usize;
-            p.int_y = ((pt.y + 1.0) / 2.0 * (MAX_INT_COORD - 1) as f64).round() as usize;
+            p.int_x = int_coord(pt.x);
+            p.int_y = int_coord(pt.y);
             p
         })
         .collect()
 }
 
+// A normalized coordinate on the integer grid.
+pub(crate) fn int_coord(v: f64) -> usize {
+    libm::round((v + 1.0) / 2.0 * (MAX_INT_COORD - 1) as f64) as usize
+}
+
 pub fn compute_lut(points: &[Point]) -> Vec<Vec<usize>> {
     let mut lut = vec![vec![0; LUT_SIZE]; LUT_SIZE];
 
-    for x in 0..LUT_SIZE {
-        for y in 0..LUT_SIZE {
+    for (x, row) in lut.iter_mut().enumerate() {
+        for (y, cell) in row.iter_mut().enumerate() {
             let mut min_distance = f64::INFINITY;
             let mut min_index = 0;
 
             for (i, pt) in points.iter().enumerate() {
-                let row = (pt.int_x as f64 / LUT_SCALE_FACTOR).round() as usize;
-                let col = (pt.int_y as f64 / LUT_SCALE_FACTOR).round() as usize;
+                let row = libm::round(pt.int_x as f64 / LUT_SCALE_FACTOR) as usize;
+                let col = libm::round(pt.int_y as f64 / LUT_SCALE_FACTOR) as usize;
                 let dist = (row as isize - x as isize).pow(2) + (col as isize - y as isize).pow(2);
                 if (dist as f64) < min_distance {
                     min_distance = dist as f64;
@@ -106,68 +255,306 @@ pub fn compute_lut(points: &[Point]) -> Vec<Vec<usize>> {
                 }
             }
 
-            lut[x][y] = min_index;
+            *cell = min_index;
         }
     }
 
     lut
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MatchCounters {
+    /// `cloud_distance` evaluations, one per start index and direction tried.
+    pub distance_calls: usize,
+    /// Start indices whose lower bound was already above the best distance.
+    pub starts_skipped: usize,
+    /// `cloud_distance` evaluations abandoned before matching every point.
+    pub abandoned: usize,
+}
+
 pub fn cloud_match(candidate: &PointCloud, template: &PointCloud, min_so_far: f64) -> f64 {
-    let n = candidate.points.len();
-    let step = (n as f64).sqrt().floor() as usize;
+    cloud_match_counted(
+        candidate,
+        template,
+        min_so_far,
+        &mut MatchCounters::default(),
+    )
+}
 
-    let lb1 = compute_lower_bound(&candidate.points, &template.points, step, &template.lut);
-    let lb2 = compute_lower_bound(&template.points, &candidate.points, step, &candidate.lut);
+pub fn cloud_match_counted(
+    candidate: &PointCloud,
+    template: &PointCloud,
+    min_so_far: f64,
+    counters: &mut MatchCounters,
+) -> f64 {
+    cloud_match_as::<f64>(candidate, template, min_so_far, counters)
+}
 
-    let mut min_val = min_so_far;
+// Same as `cloud_match_counted`, computing distances in `S`. Returns
+// `min_so_far` unchanged when no start index beats it.
+pub fn cloud_match_as<S: Scalar>(
+    candidate: &PointCloud,
+    template: &PointCloud,
+    min_so_far: f64,
+    counters: &mut MatchCounters,
+) -> f64 {
+    let candidate_coords = S::cloud_coords(candidate);
+    let template_coords = S::cloud_coords(template);
+    match_views(
+        &CloudView {
+            points: &candidate.points,
+            coords: candidate_coords,
+            nearest: |pt: &Point| lut_nearest(pt, &candidate.lut),
+        },
+        &CloudView {
+            points: &template.points,
+            coords: template_coords,
+            nearest: |pt: &Point| lut_nearest(pt, &template.lut),
+        },
+        min_so_far,
+        counters,
+    )
+}
+
+// A normalized cloud as the matcher sees it, however it is stored: its points,
+// their coordinates in `S` and the index of its point nearest to a point's
+// LUT cell.
+pub(crate) struct CloudView<'a, S, L> {
+    pub points: &'a [Point],
+    pub coords: &'a Coords<S>,
+    pub nearest: L,
+}
+
+pub(crate) fn match_views<S, L1, L2>(
+    candidate: &CloudView<S, L1>,
+    template: &CloudView<S, L2>,
+    min_so_far: f64,
+    counters: &mut MatchCounters,
+) -> f64
+where
+    S: Scalar,
+    L1: Fn(&Point) -> usize,
+    L2: Fn(&Point) -> usize,
+{
+    let n = candidate.points.len();
+    // A cloud built from an empty gesture has nothing to match.
+    if n == 0 || template.points.is_empty() {
+        return min_so_far;
+    }
+    let step = libm::floor(libm::sqrt(n as f64)) as usize;
+
+    let lb1 = compute_lower_bound(
+        candidate.points,
+        candidate.coords,
+        template.coords,
+        step,
+        &template.nearest,
+    );
+    let lb2 = compute_lower_bound(
+        template.points,
+        template.coords,
+        candidate.coords,
+        step,
+        &candidate.nearest,
+    );
 
-    for (i, &lb) in lb1.iter().enumerate().step_by(step) {
-        if lb < min_val {
-            min_val = min_val.min(cloud_distance(
-                &candidate.points,
-                &template.points,
-                i,
-                min_val,
-            ));
+    let mut min_val = S::from_distance(min_so_far);
+    let mut improved = false;
+
+    for (pts1, pts2, lb) in [
+        (candidate.coords, template.coords, lb1),
+        (template.coords, candidate.coords, lb2),
+    ] {
+        // lb[j] bounds the distance when starting at point j * step.
+        for (i, &lb) in (0..n).step_by(step).zip(lb.iter()) {
+            if lb < min_val {
+                let dist = greedy_match_as(pts1, pts2, i, min_val, |_, _, _, _| {});
+                counters.distance_calls += 1;
+                if dist < min_val {
+                    min_val = dist;
+                    improved = true;
+                } else {
+                    counters.abandoned += 1;
+                }
+            } else {
+                counters.starts_skipped += 1;
+            }
         }
     }
 
-    for (i, &lb) in lb2.iter().enumerate().step_by(step) {
-        if lb < min_val {
-            min_val = min_val.min(cloud_distance(
-                &template.points,
-                &candidate.points,
-                i,
-                min_val,
-            ));
+    if improved {
+        min_val.to_distance()
+    } else {
+        min_so_far
+    }
+}
+
+// The minimum-cost bijection between the two clouds, as an exact reference
+// for the greedy matching. The summed squared distances are scaled by the mean
+// $Q weight so distances compare with `cloud_match`. Returns `min_so_far`
+// unchanged when the matching does not beat it.
+pub fn optimal_match(
+    candidate: &PointCloud,
+    template: &PointCloud,
+    min_so_far: f64,
+    counters: &mut MatchCounters,
+) -> f64 {
+    let (pts1, pts2) = if candidate.points.len() <= template.points.len() {
+        (candidate.coords(), template.coords())
+    } else {
+        (template.coords(), candidate.coords())
+    };
+    let (_, total) = assignment::min_cost_assignment(pts1.len(), pts2.len(), |i, j| {
+        sqr_distance(pts1.get(i), pts2.get(j))
+    });
+    counters.distance_calls += 1;
+
+    let dist = total * (pts1.len() + 1) as f64 / 2.0;
+    if dist < min_so_far {
+        dist
+    } else {
+        min_so_far
+    }
+}
+
+// $Q greedy matching with the stroke features of each point, weighted by
+// `weights`, added to the squared point distance. There are no lower bounds
+// for the combined distance, so every start index is tried. Returns
+// `min_so_far` unchanged when no start index beats it.
+pub fn stroke_match(
+    candidate: &PointCloud,
+    template: &PointCloud,
+    weights: &StrokeFeatures,
+    min_so_far: f64,
+    counters: &mut MatchCounters,
+) -> f64 {
+    let candidate_features = stroke_features(&candidate.points);
+    let template_features = stroke_features(&template.points);
+    let mut min_val = min_so_far;
+
+    let candidate_side = (&candidate.points, &candidate_features);
+    let template_side = (&template.points, &template_features);
+
+    for ((pts1, features1), (pts2, features2)) in [
+        (candidate_side, template_side),
+        (template_side, candidate_side),
+    ] {
+        let cost = |i: usize, j: usize| {
+            let ([dx1, dy1, order1], [dx2, dy2, order2]) = (features1[i], features2[j]);
+            sqr_euclidean_distance(&pts1[i], &pts2[j])
+                + weights.direction * ((dx1 - dx2) * (dx1 - dx2) + (dy1 - dy2) * (dy1 - dy2))
+                + weights.order * (order1 - order2) * (order1 - order2)
+        };
+        let n = pts1.len();
+        let step = (libm::floor(libm::sqrt(n as f64)) as usize).max(1);
+        for start in (0..n).step_by(step) {
+            let mut matched = vec![false; pts2.len()];
+            let mut i = start;
+            let mut sum = 0.0;
+            for weight in (1..=n).rev().take(pts2.len()) {
+                let (j, dist) = (0..pts2.len())
+                    .filter(|&j| !matched[j])
+                    .map(|j| (j, cost(i, j)))
+                    .fold((0, f64::INFINITY), |best, next| {
+                        if next.1 < best.1 {
+                            next
+                        } else {
+                            best
+                        }
+                    });
+                matched[j] = true;
+                sum += weight as f64 * dist;
+                if sum >= min_val {
+                    break;
+                }
+                i = (i + 1) % n;
+            }
+            counters.distance_calls += 1;
+            if sum < min_val {
+                min_val = sum;
+            } else {
+                counters.abandoned += 1;
+            }
         }
     }
 
     min_val
 }
 
-fn cloud_distance(pts1: &[Point], pts2: &[Point], start: usize, min_so_far: f64) -> f64 {
+// Per point, the unit direction of travel within its stroke and the stroke's
+// place in drawing order, from 0 for the first stroke to 1 for the last.
+pub fn stroke_features(points: &[Point]) -> Vec<[f64; 3]> {
+    let last = (stroke_count(points).max(2) - 1) as f64;
+    points
+        .chunk_by(|a, b| a.id == b.id)
+        .enumerate()
+        .flat_map(|(k, stroke)| {
+            (0..stroke.len()).map(move |i| {
+                let prev = &stroke[i.saturating_sub(1)];
+                let next = &stroke[(i + 1).min(stroke.len() - 1)];
+                let (dx, dy) = (next.x - prev.x, next.y - prev.y);
+                let length = libm::sqrt(dx * dx + dy * dy);
+                if length > 0.0 {
+                    [dx / length, dy / length, k as f64 / last]
+                } else {
+                    [0.0, 0.0, k as f64 / last]
+                }
+            })
+        })
+        .collect()
+}
+
+pub fn stroke_count(points: &[Point]) -> usize {
+    points.chunk_by(|a, b| a.id == b.id).count()
+}
+
+// Matches every point of `pts1`, starting at `start`, to its nearest unmatched
+// point of `pts2`, reporting each pair with its weight and squared distance.
+pub(crate) fn greedy_match<F>(
+    pts1: &[Point],
+    pts2: &[Point],
+    start: usize,
+    min_so_far: f64,
+    on_match: F,
+) -> f64
+where
+    F: FnMut(usize, usize, usize, f64),
+{
+    greedy_match_as(
+        &Coords::new(pts1),
+        &Coords::new(pts2),
+        start,
+        min_so_far,
+        on_match,
+    )
+}
+
+fn greedy_match_as<S, F>(
+    pts1: &Coords<S>,
+    pts2: &Coords<S>,
+    start: usize,
+    min_so_far: S,
+    mut on_match: F,
+) -> S
+where
+    S: Scalar,
+    F: FnMut(usize, usize, usize, S),
+{
     let n = pts1.len();
-    let mut unmatched = (0..pts2.len()).collect::<Vec<_>>();
+    // Matched points are excluded by penalty rather than removed, so the
+    // nearest-point search always runs over the same contiguous arrays.
+    let mut penalty = vec![S::ZERO; pts2.len()];
+    let mut unmatched = pts2.len();
     let mut i = start;
     let mut weight = n;
-    let mut sum = 0.0;
+    let mut sum = S::ZERO;
 
     loop {
-        let mut min_dist = f64::INFINITY;
-        let mut min_index = 0;
-
-        for (j, &k) in unmatched.iter().enumerate() {
-            let d = sqr_euclidean_distance(&pts1[i], &pts2[k]);
-            if d < min_dist {
-                min_dist = d;
-                min_index = j;
-            }
-        }
-
-        let _matched_index = unmatched.remove(min_index);
-        sum += weight as f64 * min_dist;
+        let (matched_index, min_dist) = S::nearest(pts1.get(i), pts2, &penalty);
+        penalty[matched_index] = S::MATCHED;
+        unmatched -= 1;
+        on_match(i, matched_index, weight, min_dist);
+        sum = sum + S::from_usize(weight) * min_dist;
 
         if sum >= min_so_far {
             return sum;
@@ -176,7 +563,7 @@ fn cloud_distance(pts1: &[Point], pts2: &[Point], start: usize, min_so_far: f64)
         weight -= 1;
         i = (i + 1) % n;
 
-        if i == start || unmatched.is_empty() {
+        if i == start || unmatched == 0 {
             break;
         }
     }
@@ -184,32 +571,44 @@ fn cloud_distance(pts1: &[Point], pts2: &[Point], start: usize, min_so_far: f64)
     sum
 }
 
-fn compute_lower_bound(
+fn compute_lower_bound<S: Scalar>(
     pts1: &[Point],
-    pts2: &[Point],
+    coords1: &Coords<S>,
+    coords2: &Coords<S>,
     step: usize,
-    lut: &[Vec<usize>],
-) -> Vec<f64> {
+    nearest: impl Fn(&Point) -> usize,
+) -> Vec<S> {
     let n = pts1.len();
-    let mut lb = vec![0.0; n / step + 1];
-    let mut sat = vec![0.0; n];
+    let mut lb = vec![S::ZERO; n / step + 1];
+    let mut sat = vec![S::ZERO; n];
 
     for i in 0..n {
-        let x = (pts1[i].int_x as f64 / LUT_SCALE_FACTOR).round() as usize;
-        let y = (pts1[i].int_y as f64 / LUT_SCALE_FACTOR).round() as usize;
-        let index = lut[x][y];
-        let d = sqr_euclidean_distance(&pts1[i], &pts2[index]);
+        let d = sqr_distance(coords1.get(i), coords2.get(nearest(&pts1[i])));
         sat[i] = if i == 0 { d } else { sat[i - 1] + d };
-        lb[0] += (n - i) as f64 * d;
+        lb[0] = lb[0] + S::from_usize(n - i) * d;
     }
 
     for (i, j) in (step..n).step_by(step).zip(1..) {
-        lb[j] = lb[0] + i as f64 * sat[n - 1] - n as f64 * sat[i - 1];
+        lb[j] = lb[0] + S::from_usize(i) * sat[n - 1] - S::from_usize(n) * sat[i - 1];
     }
 
     lb
 }
 
+// Index of the point of the cloud behind `lut` nearest to `pt`, to LUT
+// resolution.
+pub fn lut_nearest(pt: &Point, lut: &[Vec<usize>]) -> usize {
+    let (x, y) = lut_cell(pt);
+    lut[x][y]
+}
+
+// The LUT row and column `pt` falls in. Points on the far edge of the grid
+// round up past the last row or column, so they go in it.
+pub fn lut_cell(pt: &Point) -> (usize, usize) {
+    let cell = |v: usize| (libm::round(v as f64 / LUT_SCALE_FACTOR) as usize).min(LUT_SIZE - 1);
+    (cell(pt.int_x), cell(pt.int_y))
+}
+
 pub fn path_length(points: &[Point]) -> f64 {
     points
         .windows(2)
@@ -227,7 +626,7 @@ pub fn centroid(points: &[Point]) -> Point {
 }
 
 pub fn euclidean_distance(pt1: &Point, pt2: &Point) -> f64 {
-    sqr_euclidean_distance(pt1, pt2).sqrt()
+    libm::sqrt(sqr_euclidean_distance(pt1, pt2))
 }
 
 pub fn sqr_euclidean_distance(pt1: &Point, pt2: &Point) -> f64 {
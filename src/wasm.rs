@@ -0,0 +1,156 @@
+// Browser bindings. The crate is an rlib by default, so build the module with
+// `cargo rustc --lib --crate-type cdylib --target wasm32-unknown-unknown --features wasm`
+// and run `wasm-bindgen` on the output.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use js_sys::Float64Array;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::clock::Clock;
+use crate::error::QDollarError;
+use crate::{Point, QDollarRecognizer};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// Reads `performance.now()`, available in browsers and Node.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PerformanceClock;
+
+impl Clock for PerformanceClock {
+    fn now(&self) -> Duration {
+        Duration::from_secs_f64(performance_now().max(0.0) / 1000.0)
+    }
+}
+
+impl From<QDollarError> for JsValue {
+    fn from(error: QDollarError) -> Self {
+        JsError::new(&error.to_string()).into()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsPoint {
+    Tuple(f64, f64, f64),
+    Object { x: f64, y: f64, id: f64 },
+}
+
+#[derive(Serialize)]
+struct JsResult<'a> {
+    name: &'a str,
+    score: f64,
+    /// Milliseconds, like `performance.now()`.
+    time: f64,
+}
+
+#[wasm_bindgen(js_name = QDollarRecognizer)]
+pub struct WasmRecognizer {
+    inner: QDollarRecognizer,
+}
+
+impl Default for WasmRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(js_class = QDollarRecognizer)]
+impl WasmRecognizer {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let mut inner = QDollarRecognizer::new();
+        inner.set_clock(Arc::new(PerformanceClock));
+        WasmRecognizer { inner }
+    }
+
+    /// `points` is a Float64Array of `[x, y, id, x, y, id, ...]`, or an array
+    /// of `[x, y, id]` triples or `{x, y, id}` objects.
+    #[wasm_bindgen(js_name = addGesture)]
+    pub fn add_gesture(&mut self, name: String, points: JsValue) -> Result<usize, JsValue> {
+        let points = extract_points(points)?;
+        Ok(self.inner.add_gesture(name, points)?)
+    }
+
+    /// Returns up to `n` (default 1) `{name, score, time}` objects, best first.
+    pub fn recognize(&self, points: JsValue, n: Option<usize>) -> Result<JsValue, JsValue> {
+        let points = extract_points(points)?;
+        let results = self.inner.recognize_n_best(&points, n.unwrap_or(1))?;
+        let results: Vec<JsResult> = results
+            .iter()
+            .map(|result| JsResult {
+                name: &result.name,
+                score: result.score,
+                time: result.time.as_secs_f64() * 1000.0,
+            })
+            .collect();
+        serde_wasm_bindgen::to_value(&results).map_err(JsValue::from)
+    }
+
+    #[wasm_bindgen(js_name = deleteUserGestures)]
+    pub fn delete_user_gestures(&mut self) -> usize {
+        self.inner.delete_user_gestures()
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        Ok(self.inner.to_json()?)
+    }
+
+    #[wasm_bindgen(js_name = loadJson)]
+    pub fn load_json(&mut self, json: &str) -> Result<usize, JsValue> {
+        Ok(self.inner.load_json(json)?)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.inner.user_gestures().len()
+    }
+}
+
+fn extract_points(value: JsValue) -> Result<Vec<Point>, JsValue> {
+    if let Some(array) = value.dyn_ref::<Float64Array>() {
+        let flat = array.to_vec();
+        if flat.len() % 3 != 0 {
+            return Err(JsError::new(&format!(
+                "expected a Float64Array of [x, y, id] triples, got {} values",
+                flat.len()
+            ))
+            .into());
+        }
+        return flat
+            .chunks_exact(3)
+            .map(|p| Ok(Point::new(p[0], p[1], stroke_id(p[2])?)))
+            .collect();
+    }
+
+    let points: Vec<JsPoint> = serde_wasm_bindgen::from_value(value).map_err(|_| {
+        JsError::new("points must be a Float64Array or an array of [x, y, id] or {x, y, id}")
+    })?;
+    points
+        .into_iter()
+        .map(|point| match point {
+            JsPoint::Tuple(x, y, id) | JsPoint::Object { x, y, id } => {
+                Ok(Point::new(x, y, stroke_id(id)?))
+            }
+        })
+        .collect()
+}
+
+fn stroke_id(value: f64) -> Result<u32, JsValue> {
+    if value.fract() != 0.0 || value < 0.0 || value > u32::MAX as f64 {
+        return Err(JsError::new(&format!(
+            "stroke id must be a non-negative integer, got {}",
+            value
+        ))
+        .into());
+    }
+    Ok(value as u32)
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "capi")]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn test_c_program() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let target_dir = out_dir.join("capi-target");
+
+    // Only the C API package, so the libraries don't pull in libpython and
+    // friends.
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .args(["build", "-p", "qdollar-capi"])
+        .arg("--manifest-path")
+        .arg(root.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "building the C libraries failed");
+    let lib_dir = target_dir.join("debug");
+
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    for (kind, library) in [
+        ("static", lib_dir.join("libqdollar_capi.a")),
+        ("shared", lib_dir.join("libqdollar_capi.so")),
+    ] {
+        let exe = out_dir.join(format!("capi_test_{}", kind));
+        let status = Command::new(&cc)
+            .arg(root.join("tests/capi/capi_test.c"))
+            .arg("-I")
+            .arg(root.join("include"))
+            .arg(&library)
+            .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+            .args(["-lpthread", "-ldl", "-lm", "-o"])
+            .arg(&exe)
+            .status()
+            .expect("failed to run the C compiler");
+        assert!(
+            status.success(),
+            "compiling the C test program failed ({})",
+            kind
+        );
+
+        let output = Command::new(&exe)
+            .arg(out_dir.join(format!("capi_templates_{}.json", kind)))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            kind,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+    }
+}
@@ -0,0 +1,84 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "qdollar.h"
+
+#define CHECK(cond)                                                            \
+  do {                                                                         \
+    if (!(cond)) {                                                             \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+      return 1;                                                                \
+    }                                                                          \
+  } while (0)
+
+static const QDollarPoint SQUARE[] = {
+    {0.0, 0.0, 1}, {0.0, 1.0, 1}, {1.0, 1.0, 1}, {1.0, 0.0, 1}, {0.0, 0.0, 1},
+};
+
+static const QDollarPoint TRIANGLE[] = {
+    {0.0, 0.0, 1}, {0.5, 1.0, 1}, {1.0, 0.0, 1}, {0.0, 0.0, 1},
+};
+
+#define LEN(a) (sizeof(a) / sizeof((a)[0]))
+
+int main(int argc, char **argv) {
+  if (argc != 2) {
+    fprintf(stderr, "usage: %s <templates.json>\n", argv[0]);
+    return 2;
+  }
+
+  char name[32];
+  double score = 0.0;
+  size_t count = 0;
+
+  QDollarRecognizer *recognizer = qdollar_recognizer_new();
+  CHECK(recognizer != NULL);
+  CHECK(qdollar_recognize(recognizer, SQUARE, LEN(SQUARE), name, sizeof(name), &score) ==
+        Q_DOLLAR_STATUS_NO_REGISTERED_GESTURES);
+
+  CHECK(qdollar_add_gesture(recognizer, "square", SQUARE, LEN(SQUARE), &count) ==
+        Q_DOLLAR_STATUS_OK);
+  CHECK(count == 1);
+  CHECK(qdollar_add_gesture(recognizer, "triangle", TRIANGLE, LEN(TRIANGLE), &count) ==
+        Q_DOLLAR_STATUS_OK);
+  CHECK(count == 2);
+  CHECK(qdollar_add_gesture(recognizer, NULL, TRIANGLE, LEN(TRIANGLE), NULL) ==
+        Q_DOLLAR_STATUS_NULL_POINTER);
+  CHECK(qdollar_add_gesture(recognizer, "\xff", TRIANGLE, LEN(TRIANGLE), NULL) ==
+        Q_DOLLAR_STATUS_INVALID_STRING);
+  /* Empty gestures are rejected, so they never become templates. */
+  CHECK(qdollar_add_gesture(recognizer, "empty", TRIANGLE, 0, &count) ==
+        Q_DOLLAR_STATUS_EMPTY_GESTURE);
+  CHECK(qdollar_add_gesture(recognizer, "empty", NULL, 0, &count) ==
+        Q_DOLLAR_STATUS_EMPTY_GESTURE);
+  CHECK(count == 2);
+
+  CHECK(qdollar_recognize(recognizer, TRIANGLE, LEN(TRIANGLE), name, sizeof(name), &score) ==
+        Q_DOLLAR_STATUS_OK);
+  CHECK(strcmp(name, "triangle") == 0);
+  CHECK(score > 0.9);
+  CHECK(qdollar_recognize(recognizer, TRIANGLE, LEN(TRIANGLE), name, 4, &score) ==
+        Q_DOLLAR_STATUS_BUFFER_TOO_SMALL);
+  CHECK(qdollar_recognize(recognizer, TRIANGLE, 0, name, sizeof(name), &score) ==
+        Q_DOLLAR_STATUS_EMPTY_GESTURE);
+  CHECK(qdollar_recognize(recognizer, NULL, 0, name, sizeof(name), &score) ==
+        Q_DOLLAR_STATUS_EMPTY_GESTURE);
+
+  CHECK(qdollar_save(recognizer, argv[1]) == Q_DOLLAR_STATUS_OK);
+  qdollar_recognizer_free(recognizer);
+
+  recognizer = qdollar_recognizer_new();
+  CHECK(qdollar_load(recognizer, "/nonexistent/templates.json") == Q_DOLLAR_STATUS_IO);
+  CHECK(qdollar_load(recognizer, argv[1]) == Q_DOLLAR_STATUS_OK);
+  CHECK(qdollar_recognize(recognizer, SQUARE, LEN(SQUARE), name, sizeof(name), &score) ==
+        Q_DOLLAR_STATUS_OK);
+  CHECK(strcmp(name, "square") == 0);
+  qdollar_recognizer_free(recognizer);
+  qdollar_recognizer_free(NULL);
+
+  CHECK(strcmp(qdollar_status_message(Q_DOLLAR_STATUS_IO), "I/O error") == 0);
+  CHECK(strcmp(qdollar_status_message(Q_DOLLAR_STATUS_PANIC), "internal error") == 0);
+
+  printf("ok\n");
+  return 0;
+}
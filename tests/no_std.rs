@@ -0,0 +1,50 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+const EMBEDDED_TARGET: &str = "thumbv7em-none-eabihf";
+
+fn target_installed(target: &str) -> bool {
+    Command::new("rustc")
+        .args(["--print", "target-libdir", "--target", target])
+        .output()
+        .map(|output| {
+            let libdir = String::from_utf8_lossy(&output.stdout);
+            output.status.success() && PathBuf::from(libdir.trim()).exists()
+        })
+        .unwrap_or(false)
+}
+
+fn build_without_std(target: Option<&str>) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut command = Command::new(cargo);
+    command
+        .args(["build", "--lib", "--no-default-features"])
+        .arg("--manifest-path")
+        .arg(root.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("no-std-target"));
+    if let Some(target) = target {
+        command.args(["--target", target]);
+    }
+    let status = command.status().expect("failed to run cargo");
+    assert!(status.success(), "no_std build failed for {:?}", target);
+}
+
+// Catches std-only code even without the embedded target.
+#[test]
+fn test_builds_without_std() {
+    build_without_std(None);
+}
+
+#[test]
+fn test_builds_for_embedded_target() {
+    assert!(
+        target_installed(EMBEDDED_TARGET),
+        "{0} is not installed; add it with `rustup target add {0}`",
+        EMBEDDED_TARGET
+    );
+    build_without_std(Some(EMBEDDED_TARGET));
+}
@@ -0,0 +1,84 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use js_sys::{Array, Float64Array, Reflect, JSON};
+use qdollar::wasm::WasmRecognizer;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const SQUARE: [f64; 15] = [
+    0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0,
+];
+const TRIANGLE: [f64; 12] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.5, 1.0, 0.0, 0.0, 0.0, 0.0];
+
+fn flat(points: &[f64]) -> JsValue {
+    Float64Array::from(points).into()
+}
+
+fn field(value: &JsValue, key: &str) -> JsValue {
+    Reflect::get(value, &key.into()).unwrap()
+}
+
+fn recognizer() -> WasmRecognizer {
+    let mut recognizer = WasmRecognizer::new();
+    recognizer
+        .add_gesture("square".into(), flat(&SQUARE))
+        .unwrap();
+    recognizer
+        .add_gesture("triangle".into(), flat(&TRIANGLE))
+        .unwrap();
+    recognizer
+}
+
+#[wasm_bindgen_test]
+fn recognizes_float64_array() {
+    let results: Array = recognizer().recognize(flat(&SQUARE), None).unwrap().into();
+    assert_eq!(results.length(), 1);
+    assert_eq!(field(&results.get(0), "name"), "square");
+    assert!(field(&results.get(0), "time").as_f64().unwrap() >= 0.0);
+}
+
+#[wasm_bindgen_test]
+fn accepts_triples_and_objects() {
+    let recognizer = recognizer();
+    let triples = JSON::parse("[[0,0,0],[1,0,0],[0.5,1,0],[0,0,0]]").unwrap();
+    let objects = JSON::parse(
+        r#"[{"x":0,"y":0,"id":0},{"x":1,"y":0,"id":0},{"x":0.5,"y":1,"id":0},{"x":0,"y":0,"id":0}]"#,
+    )
+    .unwrap();
+    for points in [triples, objects] {
+        let results: Array = recognizer.recognize(points, None).unwrap().into();
+        assert_eq!(field(&results.get(0), "name"), "triangle");
+    }
+}
+
+#[wasm_bindgen_test]
+fn returns_n_best_per_gesture() {
+    let mut recognizer = recognizer();
+    recognizer
+        .add_gesture("square".into(), flat(&SQUARE))
+        .unwrap();
+    let results: Array = recognizer.recognize(flat(&SQUARE), Some(5)).unwrap().into();
+    assert_eq!(results.length(), 2);
+    assert_eq!(field(&results.get(0), "name"), "square");
+    assert_eq!(field(&results.get(1), "name"), "triangle");
+}
+
+#[wasm_bindgen_test]
+fn rejects_malformed_points() {
+    let recognizer = recognizer();
+    assert!(recognizer.recognize(flat(&[0.0, 0.0]), None).is_err());
+    assert!(recognizer.recognize(flat(&[0.0, 0.0, 0.5]), None).is_err());
+    assert!(recognizer.recognize(JsValue::from("square"), None).is_err());
+}
+
+#[wasm_bindgen_test]
+fn round_trips_json() {
+    let json = recognizer().to_json().unwrap();
+    let mut loaded = WasmRecognizer::new();
+    assert_eq!(loaded.load_json(&json).unwrap(), 2);
+    assert_eq!(loaded.length(), 2);
+    assert!(loaded.load_json("{").is_err());
+    assert!(WasmRecognizer::new()
+        .recognize(flat(&SQUARE), None)
+        .is_err());
+}